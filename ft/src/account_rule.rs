@@ -0,0 +1,172 @@
+use near_sdk::serde::Deserialize;
+
+use crate::*;
+
+/// Upper bound on the receivers of one `AllowList` rule.
+pub const MAX_ALLOW_LIST_LEN: usize = 100;
+
+/// Restricts whom an account can send tokens to. Accounts without a rule use `AllowAll`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum AccountRule {
+    AllowAll,
+    DenyAll,
+    /// Only these receivers are allowed.
+    AllowList(Vec<AccountId>),
+    /// The account can receive tokens but never send them, like a vault.
+    ReceiveOnly,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_account_rule(&mut self, account_id: ValidAccountId, rule: AccountRule) {
+        self.assert_owner();
+        match rule {
+            AccountRule::AllowAll => {
+                self.account_rules.remove(account_id.as_ref());
+            }
+            AccountRule::AllowList(ref receivers) => {
+                assert!(receivers.len() <= MAX_ALLOW_LIST_LEN, "Allow list is too long");
+                self.account_rules.insert(account_id.as_ref(), &rule);
+            }
+            AccountRule::DenyAll | AccountRule::ReceiveOnly => {
+                self.account_rules.insert(account_id.as_ref(), &rule);
+            }
+        }
+    }
+
+    pub fn get_account_rule(&self, account_id: ValidAccountId) -> AccountRule {
+        self.account_rules.get(account_id.as_ref()).unwrap_or(AccountRule::AllowAll)
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_transfer_allowed(&self, sender_id: &AccountId, receiver_id: &AccountId) {
+        self.check_transfer_allowed(sender_id, receiver_id)
+            .unwrap_or_else(|error| env::panic(error.as_bytes()));
+    }
+
+    pub(crate) fn check_transfer_allowed(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> Result<(), String> {
+        if self.internal_is_system_account(sender_id) {
+            return Ok(());
+        }
+        let allowed = match self.account_rules.get(sender_id) {
+            None | Some(AccountRule::AllowAll) => true,
+            Some(AccountRule::DenyAll) => false,
+            Some(AccountRule::AllowList(receivers)) => receivers.contains(receiver_id),
+            Some(AccountRule::ReceiveOnly) => {
+                return Err(format!("Account {} is receive-only", sender_id))
+            }
+        };
+        if !allowed {
+            return Err(format!("Account {} cannot transfer to {}", sender_id, receiver_id));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, TOTAL_SUPPLY};
+
+    #[test]
+    #[should_panic(expected = "cannot transfer to")]
+    fn test_deny_all_sender() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_account_rule(accounts(2), AccountRule::DenyAll);
+        contract.ft_transfer(accounts(1), U128(10), None);
+    }
+
+    #[test]
+    fn test_allow_list_sender() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_account_rule(accounts(2), AccountRule::AllowList(vec![accounts(1).into()]));
+        contract.ft_transfer(accounts(1), U128(10), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot transfer to")]
+    fn test_allow_list_rejects_unlisted_receiver() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_account_rule(accounts(2), AccountRule::AllowList(vec![accounts(1).into()]));
+        contract.ft_transfer(accounts(3), U128(10), None);
+    }
+
+    #[test]
+    fn test_receive_only_account_receives() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_account_rule(accounts(1), AccountRule::ReceiveOnly);
+        contract.ft_transfer(accounts(1), U128(10), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account bob is receive-only")]
+    fn test_receive_only_account_cannot_send() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_account_rule(accounts(1), AccountRule::ReceiveOnly);
+        contract.ft_transfer(accounts(1), U128(10), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_transfer(accounts(2), U128(5), None);
+    }
+}
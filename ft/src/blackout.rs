@@ -0,0 +1,119 @@
+use crate::*;
+
+/// Upper bound on the stored blackout windows, since every transfer checks all of them.
+pub const MAX_BLACKOUT_WINDOWS: usize = 20;
+
+/// Period in which transfers are rejected. Block timestamps in nanoseconds, `end` exclusive.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BlackoutWindow {
+    pub start: U64,
+    pub end: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Rejects transfers from `start` until `end`. Windows that already ended are dropped.
+    pub fn add_blackout_window(&mut self, start: U64, end: U64) {
+        self.assert_owner();
+        assert!(start.0 < end.0, "Blackout window must end after it starts");
+        let now = env::block_timestamp();
+        assert!(end.0 > now, "Blackout window must end in the future");
+        self.blackout_windows.retain(|window| window.end.0 > now);
+        assert!(self.blackout_windows.len() < MAX_BLACKOUT_WINDOWS, "Too many blackout windows");
+        self.blackout_windows.push(BlackoutWindow { start, end });
+    }
+
+    pub fn remove_blackout_window(&mut self, start: U64, end: U64) {
+        self.assert_owner();
+        let window = BlackoutWindow { start, end };
+        let len = self.blackout_windows.len();
+        self.blackout_windows.retain(|existing| existing != &window);
+        assert!(self.blackout_windows.len() < len, "Blackout window not found");
+    }
+
+    pub fn list_blackout_windows(&self) -> Vec<BlackoutWindow> {
+        self.blackout_windows.clone()
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_no_blackout(&self, sender_id: &AccountId) {
+        self.check_no_blackout(sender_id).unwrap_or_else(|error| env::panic(error.as_bytes()));
+    }
+
+    /// System accounts can send during a blackout.
+    pub(crate) fn check_no_blackout(&self, sender_id: &AccountId) -> Result<(), String> {
+        if self.internal_is_system_account(sender_id) {
+            return Ok(());
+        }
+        let now = env::block_timestamp();
+        if self.blackout_windows.iter().any(|window| window.start.0 <= now && now < window.end.0) {
+            return Err("Transfers are paused for a blackout window".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        contract.add_blackout_window(U64(100), U64(200));
+        assert_eq!(contract.list_blackout_windows().len(), 1);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        (context, contract)
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers are paused for a blackout window")]
+    fn test_transfer_inside_blackout() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(150).build());
+        contract.ft_transfer(accounts(1), U128(10), None);
+    }
+
+    #[test]
+    fn test_transfer_outside_blackout() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(99).build());
+        contract.ft_transfer(accounts(1), U128(10), None);
+        testing_env!(context.storage_usage(env::storage_usage()).block_timestamp(200).build());
+        contract.ft_transfer(accounts(1), U128(10), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 20);
+    }
+
+    #[test]
+    fn test_system_account_sends_during_blackout() {
+        let (mut context, mut contract) = setup();
+        contract.add_system_account(accounts(2));
+        testing_env!(context.block_timestamp(150).build());
+        contract.ft_transfer(accounts(1), U128(10), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers are paused for a blackout window")]
+    fn test_claim_reservation_inside_blackout() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(MINT_DEPOSIT).build());
+        let id = contract.reserve(accounts(1), U128(10), U64(1_000));
+        testing_env!(context.predecessor_account_id(accounts(1)).block_timestamp(150).build());
+        contract.claim_reservation(id, None);
+    }
+}
@@ -0,0 +1,209 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Burns `amount` of the caller's spendable tokens.
+    #[payable]
+    pub fn ft_burn(&mut self, amount: U128, memo: Option<String>) {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let amount: Balance = amount.into();
+        self.assert_below_large_burn_threshold(amount);
+        self.internal_checked_burn(account_id, amount, memo.as_deref());
+    }
+
+    /// Restricts burning to the accounts added with `add_burner`.
+    pub fn enable_burn_whitelist(&mut self) {
+        self.assert_owner();
+        if self.burn_allowed.is_none() {
+            self.burn_allowed = Some(UnorderedSet::new(b"u".to_vec()));
+        }
+    }
+
+    /// Opens burning to every holder again and forgets the listed burners.
+    pub fn disable_burn_whitelist(&mut self) {
+        self.assert_owner();
+        if let Some(mut burners) = self.burn_allowed.take() {
+            burners.clear();
+        }
+    }
+
+    pub fn add_burner(&mut self, account_id: ValidAccountId) {
+        self.assert_owner();
+        self.burn_allowed
+            .as_mut()
+            .expect("Burn whitelist is not enabled")
+            .insert(account_id.as_ref());
+    }
+
+    pub fn remove_burner(&mut self, account_id: ValidAccountId) {
+        self.assert_owner();
+        self.burn_allowed
+            .as_mut()
+            .expect("Burn whitelist is not enabled")
+            .remove(account_id.as_ref());
+    }
+
+    /// Returns the allowed burners, or `None` when anyone may burn.
+    pub fn get_burners(&self) -> Option<Vec<AccountId>> {
+        self.burn_allowed.as_ref().map(|burners| burners.to_vec())
+    }
+}
+
+impl Contract {
+    /// Burns `amount` of the spendable tokens of `account_id` after the burn whitelist and
+    /// freeze checks, the path shared by `ft_burn` and `confirm_burn`.
+    pub(crate) fn internal_checked_burn(
+        &mut self,
+        account_id: AccountId,
+        amount: Balance,
+        memo: Option<&str>,
+    ) {
+        self.check_burn(&account_id, amount).unwrap_or_else(|error| env::panic(error.as_bytes()));
+        self.internal_burn(&account_id, amount, memo);
+        self.on_tokens_burned(account_id, amount);
+    }
+
+    /// The guards of `internal_checked_burn`, including those of `internal_burn`.
+    pub(crate) fn check_burn(&self, account_id: &AccountId, amount: Balance) -> Result<(), String> {
+        if let Some(burners) = &self.burn_allowed {
+            if !burners.contains(account_id) {
+                return Err(format!("Account {} is not allowed to burn", account_id));
+            }
+        }
+        self.check_not_frozen(account_id)?;
+        self.check_refund_burn(account_id, amount)
+    }
+
+    /// The guards of `internal_burn` plus the spendable check, without the whitelist and freeze
+    /// checks. Used to burn tokens that a receiver handed back, which the holder never chose to
+    /// burn.
+    pub(crate) fn check_refund_burn(
+        &self,
+        account_id: &AccountId,
+        amount: Balance,
+    ) -> Result<(), String> {
+        if amount == 0 {
+            return Err("The amount should be a positive number".to_string());
+        }
+        self.check_no_supply_split()?;
+        self.check_spendable(account_id, amount)?;
+        if let Some(min_total_supply) = self.min_total_supply {
+            if self.token.total_supply - amount < min_total_supply {
+                return Err("Burn would drop the total supply below the minimum".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_created_receipts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        contract.ft_transfer(accounts(3), U128(100), None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_burn_is_open_by_default() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_burn(U128(40), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 60);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 40);
+    }
+
+    #[test]
+    fn test_burn_notifies_listener() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_burn(U128(10), None);
+        let receipts = get_created_receipts();
+        assert!(!receipts.iter().any(|receipt| &receipt.receiver_id == accounts(4).as_ref()));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.set_burn_listener(Some(accounts(4)));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_burn(U128(10), None);
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(4).as_ref()));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 80);
+    }
+
+    #[test]
+    fn test_allowed_burner() {
+        let (mut context, mut contract) = setup();
+        contract.enable_burn_whitelist();
+        contract.add_burner(accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_burn(U128(40), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 60);
+        assert_eq!(contract.get_burners(), Some(vec![accounts(1).into()]));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not allowed to burn")]
+    fn test_disallowed_burner() {
+        let (mut context, mut contract) = setup();
+        contract.enable_burn_whitelist();
+        contract.add_burner(accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.ft_burn(U128(40), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Burn would drop the total supply below the minimum")]
+    fn test_burn_stops_at_supply_floor() {
+        let (mut context, mut contract) = setup();
+        contract.set_min_total_supply(Some(U128(TOTAL_SUPPLY - 100)));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_burn(U128(100), None);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 100);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.ft_burn(U128(1), None);
+    }
+}
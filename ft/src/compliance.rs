@@ -0,0 +1,267 @@
+//! Transfer approval by an external compliance contract. While `compliance_contract` is set,
+//! `ft_transfer` holds the amount, asks `can_transfer` and moves the tokens in
+//! `on_compliance_checked` only if the answer is `true`. Every other way for a holder to move
+//! tokens is disabled: `ft_transfer_call` and the other transfer methods, creating and claiming
+//! reservations, and depositing and releasing escrow. Receivers can still reject
+//! reservations and senders cancel expired ones. Owner operations such as dust sweeps are not
+//! checked.
+use crate::*;
+
+const GAS_FOR_COMPLIANCE_CHECK: Gas = 10_000_000_000_000;
+const GAS_FOR_RESOLVE_COMPLIANCE: Gas = 20_000_000_000_000;
+
+#[ext_contract(ext_compliance)]
+pub trait ComplianceContract {
+    fn can_transfer(&self, sender_id: AccountId, receiver_id: AccountId, amount: U128) -> bool;
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the contract that approves every transfer. `None` makes transfers synchronous
+    /// again.
+    pub fn set_compliance_contract(&mut self, compliance_contract: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.compliance_contract = compliance_contract.map(|account_id| account_id.into());
+    }
+
+    pub fn get_compliance_contract(&self) -> Option<AccountId> {
+        self.compliance_contract.clone()
+    }
+
+    /// Releases the held amount and performs the transfer if the compliance contract
+    /// approved it, then burns the held `transfer_precision` remainder. Returns whether the
+    /// tokens moved.
+    #[private]
+    pub fn on_compliance_checked(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        remainder: U128,
+        memo: Option<String>,
+    ) -> bool {
+        let amount: Balance = amount.into();
+        let remainder: Balance = remainder.into();
+        self.pending_callbacks = self.pending_callbacks.saturating_sub(1);
+        // Closing the sender account while the check was running released the hold.
+        let held = std::cmp::min(amount + remainder, self.reserved.get(&sender_id).unwrap_or(0));
+        self.internal_release_reserved(&sender_id, held);
+        let approved = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+            PromiseResult::Failed => false,
+        };
+        if !approved {
+            log!("Compliance check rejected the transfer of {} from @{}", amount, sender_id);
+            return false;
+        }
+        // Either account may have been unregistered, or a blackout window or supply split may
+        // have started, while the check was running.
+        if !self.token.accounts.contains_key(&receiver_id)
+            || self.token.accounts.get(&sender_id).unwrap_or(0) < amount
+            || self.check_no_blackout(&sender_id).is_err()
+            || self.check_no_supply_split().is_err()
+        {
+            log!("Transfer of {} from @{} can no longer be made", amount, sender_id);
+            return false;
+        }
+        self.internal_transfer_unguarded(&sender_id, &receiver_id, amount, memo);
+        if remainder > 0 {
+            // The callback must not fail after the transfer, so a remainder that can no longer
+            // be burned stays with the sender.
+            match self.check_burn(&sender_id, remainder) {
+                Ok(()) => {
+                    self.internal_burn(&sender_id, remainder, Some("transfer precision"));
+                    self.on_tokens_burned(sender_id, remainder);
+                }
+                Err(error) => {
+                    log!("Remainder of {} stays with @{}: {}", remainder, sender_id, error)
+                }
+            }
+        }
+        true
+    }
+}
+
+impl Contract {
+    /// Runs the transfer checks, holds `amount` and the burned part of `remainder` on the
+    /// sender like a reservation and asks the compliance contract about the transfer.
+    pub(crate) fn internal_request_compliance(
+        &mut self,
+        compliance_id: &AccountId,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: Balance,
+        remainder: Balance,
+        memo: Option<String>,
+    ) {
+        assert!(
+            self.token.accounts.contains_key(&receiver_id),
+            "The account {} is not registered",
+            receiver_id
+        );
+        self.assert_memo_allowed(&receiver_id, &memo);
+        self.internal_check_transfer(&sender_id, &receiver_id, amount, Clearance::None);
+        self.check_remainder(&sender_id, amount, remainder)
+            .unwrap_or_else(|error| env::panic(error.as_bytes()));
+        let remainder = self.internal_burned_remainder(remainder);
+        let reserved = self.reserved.get(&sender_id).unwrap_or(0);
+        self.reserved.insert(&sender_id, &(reserved + amount + remainder));
+        self.pending_callbacks += 1;
+
+        ext_compliance::can_transfer(
+            sender_id.clone(),
+            receiver_id.clone(),
+            amount.into(),
+            compliance_id,
+            NO_DEPOSIT,
+            GAS_FOR_COMPLIANCE_CHECK,
+        )
+        .then(ext_self::on_compliance_checked(
+            sender_id,
+            receiver_id,
+            amount.into(),
+            remainder.into(),
+            memo,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_COMPLIANCE,
+        ));
+    }
+
+    pub(crate) fn assert_no_compliance_contract(&self) {
+        assert!(
+            self.compliance_contract.is_none(),
+            "Transfers need a compliance check, use ft_transfer"
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{
+        accounts, get_created_receipts, testing_env_with_promise_results, VMContextBuilder,
+    };
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        contract.set_compliance_contract(Some(accounts(4)));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(4).as_ref()));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        assert_eq!(contract.spendable_balance_of(accounts(2)).0, TOTAL_SUPPLY - 100);
+        (context, contract)
+    }
+
+    fn resolve(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        result: PromiseResult,
+    ) -> bool {
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(0)
+                .predecessor_account_id(accounts(0))
+                .build(),
+            result,
+        );
+        contract.on_compliance_checked(
+            accounts(2).into(),
+            accounts(1).into(),
+            U128(100),
+            U128(0),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_compliance_approves_transfer() {
+        let (mut context, mut contract) = setup();
+        assert!(resolve(&mut context, &mut contract, PromiseResult::Successful(b"true".to_vec())));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 100);
+        assert_eq!(contract.spendable_balance_of(accounts(2)).0, TOTAL_SUPPLY - 100);
+    }
+
+    #[test]
+    fn test_compliance_denies_transfer() {
+        let (mut context, mut contract) = setup();
+        assert!(!resolve(
+            &mut context,
+            &mut contract,
+            PromiseResult::Successful(b"false".to_vec())
+        ));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        assert_eq!(contract.spendable_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers need a compliance check, use ft_transfer")]
+    fn test_transfer_call_disabled_with_compliance() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        contract.ft_transfer_call(accounts(1), U128(10), None, "".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers need a compliance check, use ft_transfer")]
+    fn test_reservation_claim_disabled_with_compliance() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let id = contract.reserve(accounts(1), U128(100), U64(1_000));
+        contract.set_compliance_contract(Some(accounts(4)));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.claim_reservation(id, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers need a compliance check, use ft_transfer")]
+    fn test_reserve_disabled_with_compliance() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.reserve(accounts(1), U128(100), U64(1_000));
+    }
+
+    #[test]
+    fn test_compliance_approval_inside_blackout() {
+        let (mut context, mut contract) = setup();
+        contract.add_blackout_window(U64(100), U64(200));
+        testing_env!(context.block_timestamp(150).build());
+        assert!(!resolve(&mut context, &mut contract, PromiseResult::Successful(b"true".to_vec())));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        assert_eq!(contract.spendable_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+    }
+}
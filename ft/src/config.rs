@@ -0,0 +1,292 @@
+use near_sdk::serde::{Deserialize, Deserializer};
+
+use crate::*;
+
+/// Settings changed by `update_config`. Missing fields keep their value. For settings that
+/// can be unset, `null` clears the setting.
+#[derive(Deserialize, Default)]
+#[serde(crate = "near_sdk::serde", deny_unknown_fields)]
+pub struct ConfigPatch {
+    pub treasury_id: Option<ValidAccountId>,
+    pub mint_to_self_only: Option<bool>,
+    pub minting_paused: Option<bool>,
+    pub whole_units_only: Option<bool>,
+    pub mint_fee_yocto: Option<U128>,
+    pub emit_events: Option<bool>,
+    pub aggregate_batch_events: Option<bool>,
+    pub reject_self_transfers: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub escrow_agent: Option<Option<ValidAccountId>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub max_transfers_per_block: Option<Option<u32>>,
+    pub max_locks_per_account: Option<u32>,
+    pub same_block_guard: Option<bool>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub min_account_balance: Option<Option<U128>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub min_account_age_seconds: Option<Option<u64>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub large_transfer_threshold: Option<Option<U128>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub large_burn_threshold: Option<Option<U128>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub min_total_supply: Option<Option<U128>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub withdrawal_threshold: Option<Option<U128>>,
+    pub withdrawal_delay: Option<U64>,
+    pub max_icon_bytes: Option<u32>,
+    pub banned_memo_terms: Option<Vec<String>>,
+    pub banned_msg_prefixes: Option<Vec<String>>,
+    pub require_memo: Option<bool>,
+    pub auto_register_on_receive: Option<bool>,
+    pub storage_deposit_tolerance: Option<U128>,
+    /// Applied together with `remainder_policy`; a missing one keeps its current value.
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub transfer_precision: Option<Option<u8>>,
+    pub remainder_policy: Option<RemainderPolicy>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub compliance_contract: Option<Option<ValidAccountId>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub registry_contract: Option<Option<ValidAccountId>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub burn_listener: Option<Option<ValidAccountId>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub fallback_receiver: Option<Option<ValidAccountId>>,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    pub sponsorship_budget: Option<Option<U128>>,
+}
+
+/// Tells a `null` field (`Some(None)`) apart from a missing one (`None`).
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Deserialize::deserialize(deserializer).map(Some)
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Applies every field of `patch` in one call and logs a single `config_updated` event
+    /// naming the changed settings. Each field goes through its own setter, so it is
+    /// validated the same way. Settings kept in collections, such as blackout windows, system
+    /// accounts or the burn whitelist, have their own methods only.
+    pub fn update_config(&mut self, patch: ConfigPatch) {
+        self.assert_owner();
+        let mut fields = Vec::new();
+        if let Some(treasury_id) = patch.treasury_id {
+            self.set_treasury(treasury_id);
+            fields.push("treasury_id");
+        }
+        if let Some(enabled) = patch.mint_to_self_only {
+            self.set_mint_to_self_only(enabled);
+            fields.push("mint_to_self_only");
+        }
+        if let Some(paused) = patch.minting_paused {
+            if paused {
+                self.pause_minting();
+            } else {
+                self.resume_minting();
+            }
+            fields.push("minting_paused");
+        }
+        if let Some(enabled) = patch.whole_units_only {
+            self.set_whole_units_only(enabled);
+            fields.push("whole_units_only");
+        }
+        if let Some(mint_fee_yocto) = patch.mint_fee_yocto {
+            self.set_mint_fee(mint_fee_yocto);
+            fields.push("mint_fee_yocto");
+        }
+        if let Some(enabled) = patch.emit_events {
+            self.set_emit_events(enabled);
+            fields.push("emit_events");
+        }
+        if let Some(enabled) = patch.aggregate_batch_events {
+            self.set_aggregate_batch_events(enabled);
+            fields.push("aggregate_batch_events");
+        }
+        if let Some(enabled) = patch.reject_self_transfers {
+            self.set_reject_self_transfers(enabled);
+            fields.push("reject_self_transfers");
+        }
+        if let Some(escrow_agent) = patch.escrow_agent {
+            self.set_escrow_agent(escrow_agent);
+            fields.push("escrow_agent");
+        }
+        if let Some(max_transfers) = patch.max_transfers_per_block {
+            self.set_max_transfers_per_block(max_transfers);
+            fields.push("max_transfers_per_block");
+        }
+        if let Some(max_locks) = patch.max_locks_per_account {
+            self.set_max_locks_per_account(max_locks);
+            fields.push("max_locks_per_account");
+        }
+        if let Some(enabled) = patch.same_block_guard {
+            self.set_same_block_guard(enabled);
+            fields.push("same_block_guard");
+        }
+        if let Some(min_balance) = patch.min_account_balance {
+            self.set_min_account_balance(min_balance);
+            fields.push("min_account_balance");
+        }
+        if let Some(min_age_seconds) = patch.min_account_age_seconds {
+            self.set_min_account_age_seconds(min_age_seconds);
+            fields.push("min_account_age_seconds");
+        }
+        if let Some(threshold) = patch.large_transfer_threshold {
+            self.set_large_transfer_threshold(threshold);
+            fields.push("large_transfer_threshold");
+        }
+        if let Some(threshold) = patch.large_burn_threshold {
+            self.set_large_burn_threshold(threshold);
+            fields.push("large_burn_threshold");
+        }
+        if let Some(min_total_supply) = patch.min_total_supply {
+            self.set_min_total_supply(min_total_supply);
+            fields.push("min_total_supply");
+        }
+        if let Some(threshold) = patch.withdrawal_threshold {
+            self.set_withdrawal_threshold(threshold);
+            fields.push("withdrawal_threshold");
+        }
+        if let Some(delay) = patch.withdrawal_delay {
+            self.set_withdrawal_delay(delay);
+            fields.push("withdrawal_delay");
+        }
+        if let Some(max_icon_bytes) = patch.max_icon_bytes {
+            self.set_max_icon_bytes(max_icon_bytes);
+            fields.push("max_icon_bytes");
+        }
+        if let Some(terms) = patch.banned_memo_terms {
+            self.set_banned_memo_terms(terms);
+            fields.push("banned_memo_terms");
+        }
+        if let Some(prefixes) = patch.banned_msg_prefixes {
+            self.set_banned_msg_prefixes(prefixes);
+            fields.push("banned_msg_prefixes");
+        }
+        if let Some(enabled) = patch.require_memo {
+            self.set_require_memo(enabled);
+            fields.push("require_memo");
+        }
+        if let Some(enabled) = patch.auto_register_on_receive {
+            self.set_auto_register_on_receive(enabled);
+            fields.push("auto_register_on_receive");
+        }
+        if let Some(tolerance) = patch.storage_deposit_tolerance {
+            self.set_storage_deposit_tolerance(tolerance);
+            fields.push("storage_deposit_tolerance");
+        }
+        if patch.transfer_precision.is_some() || patch.remainder_policy.is_some() {
+            let precision = patch.transfer_precision.unwrap_or(self.transfer_precision);
+            let policy = patch.remainder_policy.unwrap_or(self.remainder_policy);
+            self.set_transfer_precision(precision, policy);
+            fields.push("transfer_precision");
+        }
+        if let Some(compliance_contract) = patch.compliance_contract {
+            self.set_compliance_contract(compliance_contract);
+            fields.push("compliance_contract");
+        }
+        if let Some(registry_contract) = patch.registry_contract {
+            self.set_registry_contract(registry_contract);
+            fields.push("registry_contract");
+        }
+        if let Some(burn_listener) = patch.burn_listener {
+            self.set_burn_listener(burn_listener);
+            fields.push("burn_listener");
+        }
+        if let Some(fallback_receiver) = patch.fallback_receiver {
+            self.set_fallback_receiver(fallback_receiver);
+            fields.push("fallback_receiver");
+        }
+        if let Some(budget) = patch.sponsorship_budget {
+            self.set_sponsorship_budget(budget);
+            fields.push("sponsorship_budget");
+        }
+        assert!(!fields.is_empty(), "Config patch is empty");
+        self.emit_contract_event(ContractEvent::ConfigUpdated(vec![ConfigUpdated {
+            owner_id: &self.owner_id,
+            fields,
+        }]));
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::serde_json;
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::memo::MAX_BANNED_MEMO_TERMS;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_update_config() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_large_transfer_threshold(Some(U128(1_000)));
+        testing_env!(context.build());
+
+        let patch: ConfigPatch = serde_json::from_str(
+            r#"{"minting_paused":true,"mint_fee_yocto":"10","large_transfer_threshold":null,
+                "max_transfers_per_block":3}"#,
+        )
+        .unwrap();
+        contract.update_config(patch);
+
+        let config = contract.get_config();
+        assert!(config.minting_paused);
+        assert_eq!(config.mint_fee_yocto.0, 10);
+        assert!(config.large_transfer_threshold.is_none());
+        assert_eq!(config.max_transfers_per_block, Some(3));
+        assert!(!config.mint_to_self_only);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"fungible_token","version":"1.0.0","event":"config_updated","data":[{"owner_id":"charlie","fields":["minting_paused","mint_fee_yocto","max_transfers_per_block","large_transfer_threshold"]}]}"#
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by the owner")]
+    fn test_update_config_by_non_owner() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.update_config(ConfigPatch { minting_paused: Some(true), ..Default::default() });
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many banned memo terms")]
+    fn test_update_config_validates_fields() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.update_config(ConfigPatch {
+            banned_memo_terms: Some(vec!["spam".to_string(); MAX_BANNED_MEMO_TERMS + 1]),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn test_update_config_transfer_precision() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let patch: ConfigPatch =
+            serde_json::from_str(r#"{"remainder_policy":"Burn","same_block_guard":true}"#).unwrap();
+        contract.update_config(patch);
+
+        let config = contract.get_config();
+        assert_eq!(config.remainder_policy, RemainderPolicy::Burn);
+        assert!(config.transfer_precision.is_none());
+        assert!(config.same_block_guard);
+    }
+}
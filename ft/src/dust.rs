@@ -0,0 +1,180 @@
+use crate::*;
+
+/// Upper bound on the accounts processed by one `sweep_dust` call.
+pub const MAX_SWEEP_BATCH: usize = 100;
+
+#[near_bindgen]
+impl Contract {
+    /// Moves every balance below `threshold`, at most one whole token, among `accounts` to the
+    /// treasury. With `unregister`, the swept accounts are also removed and the storage
+    /// deposit they paid is refunded to them, which is nothing for sponsored accounts. The
+    /// treasury, the contract account and accounts with open reservations or escrow are
+    /// skipped. With `set_aggregate_batch_events`, all sweeps are logged as one `ft_transfer`
+    /// event. Returns the number of accounts swept.
+    pub fn sweep_dust(
+        &mut self,
+        accounts: Vec<ValidAccountId>,
+        threshold: U128,
+        unregister: bool,
+    ) -> u32 {
+        self.assert_owner();
+        assert!(accounts.len() <= MAX_SWEEP_BATCH, "Too many accounts in one batch");
+        let decimals = u32::from(self.metadata.get().unwrap().decimals);
+        let whole_token = 10u128.checked_pow(decimals).unwrap_or(Balance::MAX);
+        assert!(threshold.0 <= whole_token, "Dust threshold cannot exceed one whole token");
+        let mut accounts: Vec<AccountId> =
+            accounts.into_iter().map(|account_id| account_id.into()).collect();
+        accounts.sort();
+        accounts.dedup();
+
+        let treasury_id = self.treasury_id.clone();
+        let mut swept = 0;
+        let mut aggregated: Vec<(AccountId, Balance)> = Vec::new();
+        for account_id in accounts {
+            if account_id == treasury_id
+                || account_id == env::current_account_id()
+                || self.reserved.get(&account_id).unwrap_or(0) > 0
+                || self.escrow_balances.get(&account_id).is_some()
+            {
+                continue;
+            }
+            let balance = match self.token.accounts.get(&account_id) {
+                Some(balance) if balance < threshold.0 => balance,
+                _ => continue,
+            };
+            if balance > 0 && self.aggregate_batch_events {
+                self.internal_transfer_without_event(
+                    &account_id,
+                    &treasury_id,
+                    balance,
+                    Some("dust sweep".to_string()),
+                );
+                aggregated.push((account_id.clone(), balance));
+            } else if balance > 0 {
+                self.internal_transfer_unguarded(
+                    &account_id,
+                    &treasury_id,
+                    balance,
+                    Some("dust sweep".to_string()),
+                );
+            }
+            if unregister {
+                let storage_refund = self.internal_storage_deposit_of(&account_id);
+                self.token.accounts.remove(&account_id);
+                if storage_refund > 0 {
+                    Promise::new(account_id.clone()).transfer(storage_refund);
+                }
+                self.on_account_closed(account_id, 0);
+            }
+            swept += 1;
+        }
+        if !aggregated.is_empty() {
+            self.emit_event(Nep141Event::FtTransfer(
+                aggregated
+                    .iter()
+                    .map(|(account_id, amount)| FtTransfer {
+                        old_owner_id: account_id,
+                        new_owner_id: &treasury_id,
+                        amount: (*amount).into(),
+                        memo: Some("dust sweep"),
+                    })
+                    .collect(),
+            ));
+        }
+        swept
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_created_receipts, get_logs, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_treasury(accounts(4));
+        register_account(&mut context, &mut contract, accounts(4));
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(5), None);
+        contract.ft_transfer(accounts(3), U128(500), None);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(0).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_sweep_dust() {
+        let (_context, mut contract) = setup();
+        let swept = contract.sweep_dust(vec![accounts(1), accounts(3)], U128(10), false);
+        assert_eq!(swept, 1);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 500);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 5);
+        assert!(contract.storage_balance_of(accounts(1)).is_some());
+    }
+
+    #[test]
+    fn test_sweep_dust_and_unregister() {
+        let (_context, mut contract) = setup();
+        let swept = contract.sweep_dust(vec![accounts(1), accounts(3)], U128(10), true);
+        assert_eq!(swept, 1);
+        assert!(contract.storage_balance_of(accounts(1)).is_none());
+        assert!(contract.storage_balance_of(accounts(3)).is_some());
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 5);
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(1).as_ref()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Dust threshold cannot exceed one whole token")]
+    fn test_sweep_dust_threshold_is_capped() {
+        let (_context, mut contract) = setup();
+        contract.sweep_dust(vec![accounts(3)], U128(u128::MAX), false);
+    }
+
+    #[test]
+    fn test_sweep_dust_does_not_refund_sponsored_accounts() {
+        let (mut context, mut contract) = setup();
+        contract.register_accounts_bulk(vec![accounts(5)]);
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        assert_eq!(contract.sweep_dust(vec![accounts(5)], U128(10), true), 1);
+        assert!(contract.storage_balance_of(accounts(5)).is_none());
+        let receipts = get_created_receipts();
+        assert!(!receipts.iter().any(|receipt| &receipt.receiver_id == accounts(5).as_ref()));
+    }
+
+    #[test]
+    fn test_sweep_dust_aggregates_events() {
+        let (_context, mut contract) = setup();
+        contract.set_aggregate_batch_events(true);
+        let swept = contract.sweep_dust(vec![accounts(1), accounts(3)], U128(1_000), false);
+        assert_eq!(swept, 2);
+        let events: Vec<String> =
+            get_logs().into_iter().filter(|log| log.starts_with("EVENT_JSON:")).collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0],
+            r#"EVENT_JSON:{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{"old_owner_id":"bob","new_owner_id":"eugene","amount":"5","memo":"dust sweep"},{"old_owner_id":"danny","new_owner_id":"eugene","amount":"500","memo":"dust sweep"}]}"#
+        );
+    }
+
+    #[test]
+    fn test_sweep_dust_logs_each_transfer_by_default() {
+        let (_context, mut contract) = setup();
+        contract.sweep_dust(vec![accounts(1), accounts(3)], U128(1_000), false);
+        let events = get_logs().into_iter().filter(|log| log.starts_with("EVENT_JSON:")).count();
+        assert_eq!(events, 2);
+    }
+}
@@ -0,0 +1,167 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Moves `amount` of the caller's tokens into escrow. Escrowed tokens are held by the
+    /// contract account and tracked per depositor until the escrow agent releases them.
+    /// Disabled while a compliance contract is set, like every transfer but `ft_transfer`.
+    #[payable]
+    pub fn escrow_deposit(&mut self, amount: U128) {
+        assert_at_least_one_yocto();
+        let initial_storage_usage = env::storage_usage();
+        let depositor_id = env::predecessor_account_id();
+        let escrow_id = env::current_account_id();
+        if !self.token.accounts.contains_key(&escrow_id) {
+            self.internal_register_account(&escrow_id);
+        }
+        self.internal_transfer(
+            &depositor_id,
+            &escrow_id,
+            amount.into(),
+            Some("escrow deposit".to_string()),
+        );
+        let escrowed = self.escrow_balances.get(&depositor_id).unwrap_or(0);
+        self.escrow_balances.insert(&depositor_id, &(escrowed + amount.0));
+
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+    }
+
+    /// Pays out `amount` from the escrow of `depositor_id` to `receiver_id`. Only callable by
+    /// the escrow agent. Frozen receivers, blackout windows and a running supply split block
+    /// the release like any transfer. Disabled while a compliance contract is set, since the
+    /// release moves the tokens without a check.
+    pub fn escrow_release(
+        &mut self,
+        depositor_id: ValidAccountId,
+        receiver_id: ValidAccountId,
+        amount: U128,
+    ) {
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.escrow_agent,
+            "Only the escrow agent can release escrow"
+        );
+        let escrowed = self.escrow_balances.get(depositor_id.as_ref()).unwrap_or(0);
+        assert!(amount.0 <= escrowed, "Not enough escrowed balance");
+        self.assert_no_compliance_contract();
+        self.assert_no_supply_split();
+        self.assert_not_frozen(receiver_id.as_ref());
+        if escrowed == amount.0 {
+            self.escrow_balances.remove(depositor_id.as_ref());
+        } else {
+            self.escrow_balances.insert(depositor_id.as_ref(), &(escrowed - amount.0));
+        }
+        self.internal_transfer_unguarded(
+            &env::current_account_id(),
+            receiver_id.as_ref(),
+            amount.into(),
+            Some("escrow release".to_string()),
+        );
+    }
+
+    pub fn escrow_balance_of(&self, account_id: ValidAccountId) -> U128 {
+        self.escrow_balances.get(account_id.as_ref()).unwrap_or(0).into()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_escrow_agent(Some(accounts(3)));
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.escrow_deposit(U128(500));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_escrow_deposit() {
+        let (_, contract) = setup();
+        assert_eq!(contract.escrow_balance_of(accounts(2)).0, 500);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 500);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    fn test_escrow_release() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.escrow_release(accounts(2), accounts(1), U128(300));
+        assert_eq!(contract.escrow_balance_of(accounts(2)).0, 200);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 300);
+        assert_eq!(contract.ft_balance_of(accounts(0)).0, 200);
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the escrow agent can release escrow")]
+    fn test_escrow_release_unauthorized() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.escrow_release(accounts(2), accounts(1), U128(300));
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_escrow_deposit_without_deposit() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(0).build());
+        contract.escrow_deposit(U128(100));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can't unregister the account with escrowed tokens")]
+    fn test_depositor_with_escrow_cannot_unregister() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        contract.storage_unregister(Some(true));
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers need a compliance check, use ft_transfer")]
+    fn test_escrow_release_disabled_with_compliance() {
+        let (mut context, mut contract) = setup();
+        contract.set_compliance_contract(Some(accounts(4)));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.escrow_release(accounts(2), accounts(1), U128(300));
+    }
+
+    #[test]
+    #[should_panic(expected = "is frozen")]
+    fn test_escrow_release_to_frozen_receiver() {
+        let (mut context, mut contract) = setup();
+        contract.freeze_account(accounts(1));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.escrow_release(accounts(2), accounts(1), U128(300));
+    }
+}
@@ -0,0 +1,177 @@
+//! NEP-297 formatted events, logged as `EVENT_JSON:{...}`. Balance changes use the NEP-141
+//! standard; events about this contract's own administration use `fungible_token`.
+use near_sdk::serde_json;
+
+use crate::*;
+
+const NEP141_STANDARD: &str = "nep141";
+const NEP141_VERSION: &str = "1.0.0";
+const CONTRACT_STANDARD: &str = "fungible_token";
+const CONTRACT_VERSION: &str = "1.0.0";
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct FtMint<'a> {
+    pub owner_id: &'a str,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct FtTransfer<'a> {
+    pub old_owner_id: &'a str,
+    pub new_owner_id: &'a str,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct FtBurn<'a> {
+    pub owner_id: &'a str,
+    pub amount: U128,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<&'a str>,
+}
+
+/// Settings changed together by `update_config`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct ConfigUpdated<'a> {
+    pub owner_id: &'a str,
+    pub fields: Vec<&'static str>,
+}
+
+/// Balances exchanged by `admin_swap_balances`, with the balances each account holds after it.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct BalancesSwapped<'a> {
+    pub owner_id: &'a str,
+    pub account_a: &'a str,
+    pub account_b: &'a str,
+    pub balance_a: U128,
+    pub balance_b: U128,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub(crate) enum Nep141Event<'a> {
+    FtMint(Vec<FtMint<'a>>),
+    FtTransfer(Vec<FtTransfer<'a>>),
+    FtBurn(Vec<FtBurn<'a>>),
+}
+
+/// Events outside NEP-141, logged under `CONTRACT_STANDARD`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde", tag = "event", content = "data", rename_all = "snake_case")]
+pub(crate) enum ContractEvent<'a> {
+    ConfigUpdated(Vec<ConfigUpdated<'a>>),
+    BalancesSwapped(Vec<BalancesSwapped<'a>>),
+}
+
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<E> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: E,
+}
+
+impl Contract {
+    /// Logs `event` unless the owner switched events off with `set_emit_events(false)`.
+    pub(crate) fn emit_event(&self, event: Nep141Event) {
+        self.log_event(NEP141_STANDARD, NEP141_VERSION, event);
+    }
+
+    /// Same as `emit_event` for the events of this contract's own standard.
+    pub(crate) fn emit_contract_event(&self, event: ContractEvent) {
+        self.log_event(CONTRACT_STANDARD, CONTRACT_VERSION, event);
+    }
+
+    fn log_event<E: Serialize>(&self, standard: &'static str, version: &'static str, event: E) {
+        if !self.emit_events {
+            return;
+        }
+        let log = EventLog { standard, version, event };
+        log!("EVENT_JSON:{}", serde_json::to_string(&log).unwrap());
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn event_logs() -> Vec<String> {
+        get_logs().into_iter().filter(|log| log.starts_with("EVENT_JSON:")).collect()
+    }
+
+    #[test]
+    fn test_events_emitted_by_default() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(10), Some("hi".to_string()));
+        assert_eq!(
+            event_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{{"old_owner_id":"{}","new_owner_id":"{}","amount":"10","memo":"hi"}}]}}"#,
+                accounts(2).as_ref(),
+                accounts(1).as_ref()
+            )]
+        );
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(5));
+        assert_eq!(
+            event_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"nep141","version":"1.0.0","event":"ft_mint","data":[{{"owner_id":"{}","amount":"5"}}]}}"#,
+                accounts(1).as_ref()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_silent_mode_skips_events() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_emit_events(false);
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(10), None);
+        assert!(event_logs().is_empty());
+        assert!(!get_logs().is_empty());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(5));
+        assert!(event_logs().is_empty());
+    }
+}
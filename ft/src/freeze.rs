@@ -0,0 +1,252 @@
+use crate::*;
+
+/// Upper bound on the accounts processed by one bulk freeze call, to stay within gas.
+pub const MAX_FREEZE_BATCH: usize = 100;
+
+#[near_bindgen]
+impl Contract {
+    /// Blocks all transfers from and to `account_id`.
+    pub fn freeze_account(&mut self, account_id: ValidAccountId) {
+        self.assert_owner();
+        self.internal_freeze(account_id.as_ref(), None);
+    }
+
+    /// Blocks all transfers from and to `account_id` until the block timestamp reaches
+    /// `timestamp`, in nanoseconds. The freeze lapses on its own without an unfreeze call.
+    pub fn freeze_account_until(&mut self, account_id: ValidAccountId, timestamp: U64) {
+        self.assert_owner();
+        assert!(timestamp.0 > env::block_timestamp(), "Expiry must be in the future");
+        self.internal_freeze(account_id.as_ref(), Some(timestamp.0));
+    }
+
+    pub fn unfreeze_account(&mut self, account_id: ValidAccountId) {
+        self.assert_owner();
+        self.internal_unfreeze(account_id.as_ref());
+    }
+
+    pub fn freeze_accounts_bulk(&mut self, accounts: Vec<ValidAccountId>) {
+        self.assert_owner();
+        assert!(accounts.len() <= MAX_FREEZE_BATCH, "Too many accounts in one batch");
+        for account_id in accounts {
+            self.internal_freeze(account_id.as_ref(), None);
+        }
+    }
+
+    pub fn unfreeze_accounts_bulk(&mut self, accounts: Vec<ValidAccountId>) {
+        self.assert_owner();
+        assert!(accounts.len() <= MAX_FREEZE_BATCH, "Too many accounts in one batch");
+        for account_id in accounts {
+            self.internal_unfreeze(account_id.as_ref());
+        }
+    }
+
+    pub fn is_frozen(&self, account_id: ValidAccountId) -> bool {
+        self.internal_is_frozen(account_id.as_ref())
+    }
+
+    /// Returns up to `limit` frozen accounts starting at index `from`. Accounts frozen with an
+    /// expiry stay listed until they are unfrozen, even after the freeze lapsed.
+    pub fn list_frozen(&self, from: U64, limit: u64) -> Vec<AccountId> {
+        assert!(limit <= MAX_FREEZE_BATCH as u64, "Too many accounts in one batch");
+        let frozen = self.frozen.as_vector();
+        let end = std::cmp::min(from.0.saturating_add(limit), frozen.len());
+        (from.0..end).map(|index| frozen.get(index).unwrap()).collect()
+    }
+
+    /// Returns up to `limit` blacklisted accounts, the ones frozen without an expiry, starting
+    /// at index `from` of the blacklist.
+    pub fn list_blacklisted(&self, from: U64, limit: u64) -> Vec<AccountId> {
+        assert!(limit <= MAX_FREEZE_BATCH as u64, "Too many accounts in one batch");
+        let blacklisted = self.blacklisted.as_vector();
+        let end = std::cmp::min(from.0.saturating_add(limit), blacklisted.len());
+        (from.0..end).map(|index| blacklisted.get(index).unwrap()).collect()
+    }
+}
+
+impl Contract {
+    /// Freezes the account until `until`, or blacklists it without an expiry.
+    fn internal_freeze(&mut self, account_id: &AccountId, until: Option<u64>) {
+        self.frozen.insert(account_id);
+        match until {
+            Some(until) => {
+                self.frozen_until.insert(account_id, &until);
+                self.blacklisted.remove(account_id);
+            }
+            None => {
+                self.frozen_until.remove(account_id);
+                self.blacklisted.insert(account_id);
+            }
+        }
+    }
+
+    fn internal_unfreeze(&mut self, account_id: &AccountId) {
+        self.frozen.remove(account_id);
+        self.frozen_until.remove(account_id);
+        self.blacklisted.remove(account_id);
+    }
+
+    /// Whether the account is frozen right now. Freezes with an expiry count only until then.
+    pub(crate) fn internal_is_frozen(&self, account_id: &AccountId) -> bool {
+        self.frozen.contains(account_id)
+            && self
+                .frozen_until
+                .get(account_id)
+                .map_or(true, |frozen_until| env::block_timestamp() < frozen_until)
+    }
+
+    pub(crate) fn assert_not_frozen(&self, account_id: &AccountId) {
+        self.check_not_frozen(account_id).unwrap_or_else(|error| env::panic(error.as_bytes()));
+    }
+
+    /// System accounts pass even while frozen.
+    pub(crate) fn check_not_frozen(&self, account_id: &AccountId) -> Result<(), String> {
+        if self.internal_is_system_account(account_id) || !self.internal_is_frozen(account_id) {
+            Ok(())
+        } else {
+            Err(format!("Account {} is frozen", account_id))
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_freeze_accounts_bulk() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.freeze_accounts_bulk(vec![accounts(1), accounts(3), accounts(4)]);
+        assert!(contract.is_frozen(accounts(1)));
+        assert!(contract.is_frozen(accounts(3)));
+        assert!(contract.is_frozen(accounts(4)));
+        assert!(!contract.is_frozen(accounts(2)));
+
+        contract.unfreeze_accounts_bulk(vec![accounts(1), accounts(3)]);
+        assert!(!contract.is_frozen(accounts(1)));
+        assert!(!contract.is_frozen(accounts(3)));
+        assert!(contract.is_frozen(accounts(4)));
+    }
+
+    #[test]
+    fn test_list_frozen() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.freeze_accounts_bulk(vec![accounts(1), accounts(3), accounts(4)]);
+
+        let mut listed = contract.list_frozen(U64(0), 2);
+        assert_eq!(listed.len(), 2);
+        listed.extend(contract.list_frozen(U64(2), 2));
+        listed.sort();
+        assert_eq!(
+            listed,
+            vec![accounts(1).to_string(), accounts(3).to_string(), accounts(4).to_string()]
+        );
+        assert!(contract.list_frozen(U64(3), 2).is_empty());
+    }
+
+    #[test]
+    fn test_list_blacklisted() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.block_timestamp(100).build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.freeze_accounts_bulk(vec![accounts(1), accounts(3)]);
+        contract.freeze_account_until(accounts(4), U64(200));
+
+        let mut listed = contract.list_blacklisted(U64(0), 2);
+        listed.sort();
+        assert_eq!(listed, vec![accounts(1).to_string(), accounts(3).to_string()]);
+        assert!(contract.list_blacklisted(U64(2), 2).is_empty());
+        assert_eq!(contract.list_frozen(U64(0), 3).len(), 3);
+
+        contract.freeze_account(accounts(4));
+        assert_eq!(contract.list_blacklisted(U64(0), 3).len(), 3);
+        contract.freeze_account_until(accounts(1), U64(300));
+        contract.unfreeze_account(accounts(3));
+        assert_eq!(contract.list_blacklisted(U64(0), 3), vec![accounts(4).to_string()]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many accounts in one batch")]
+    fn test_freeze_accounts_bulk_cap() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.freeze_accounts_bulk(vec![accounts(1); MAX_FREEZE_BATCH + 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "is frozen")]
+    fn test_frozen_account_cannot_transfer() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.freeze_accounts_bulk(vec![accounts(2)]);
+        contract.ft_transfer(accounts(1), U128(10), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "is frozen")]
+    fn test_freeze_until_blocks_before_expiry() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(100)
+            .build());
+        contract.freeze_account_until(accounts(2), U64(200));
+        testing_env!(context.storage_usage(env::storage_usage()).block_timestamp(199).build());
+        contract.ft_transfer(accounts(1), U128(10), None);
+    }
+
+    #[test]
+    fn test_freeze_until_lapses_after_expiry() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(100)
+            .build());
+        contract.freeze_account_until(accounts(2), U64(200));
+        assert!(contract.is_frozen(accounts(2)));
+
+        testing_env!(context.storage_usage(env::storage_usage()).block_timestamp(200).build());
+        assert!(!contract.is_frozen(accounts(2)));
+        contract.ft_transfer(accounts(1), U128(10), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by the owner")]
+    fn test_freeze_requires_owner() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.freeze_accounts_bulk(vec![accounts(3)]);
+    }
+}
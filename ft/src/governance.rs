@@ -0,0 +1,272 @@
+//! Balance-weighted voting. Creating a proposal takes a snapshot of every balance, with the
+//! proposal id as snapshot id, and every vote counts with the balance the voter held then, so
+//! tokens moved after the snapshot cannot vote twice.
+//!
+//! The snapshot is recorded lazily: the first balance change of an account while the proposal
+//! is open saves the balance from before the change, and accounts without a saved balance vote
+//! with their current one. At most `MAX_OPEN_PROPOSALS` proposals are open at once, each for at
+//! most `MAX_VOTING_PERIOD`, so the registration deposit can pay for the saved balances.
+use crate::*;
+
+/// Longest proposal description accepted, in bytes.
+pub const MAX_PROPOSAL_DESCRIPTION_LEN: usize = 1_000;
+/// Upper bound on the proposals open for voting at the same time.
+pub const MAX_OPEN_PROPOSALS: usize = 4;
+/// Longest time a proposal stays open for voting, in nanoseconds.
+pub const MAX_VOTING_PERIOD: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Proposal {
+    pub proposer_id: AccountId,
+    pub description: String,
+    /// Block timestamp in nanoseconds of the creation, whose balances weigh the votes.
+    pub snapshot_at: U64,
+    /// Block timestamp in nanoseconds after which votes are rejected.
+    pub deadline: U64,
+    pub votes_for: U128,
+    pub votes_against: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Opens a proposal weighted by the balances of now and returns its id, which is also the
+    /// id of its snapshot. The caller pays for the storage of the proposal.
+    #[payable]
+    pub fn create_proposal(&mut self, description: String, deadline: U64) -> U64 {
+        let initial_storage_usage = env::storage_usage();
+        let now = env::block_timestamp();
+        assert!(
+            description.len() <= MAX_PROPOSAL_DESCRIPTION_LEN,
+            "Proposal description is too long"
+        );
+        assert!(deadline.0 > now, "Deadline must be in the future");
+        assert!(deadline.0 - now <= MAX_VOTING_PERIOD, "Voting period is too long");
+        self.assert_no_supply_split();
+        self.open_proposals.retain(|(_, open_until)| now <= *open_until);
+        assert!(self.open_proposals.len() < MAX_OPEN_PROPOSALS, "Too many open proposals");
+
+        let id = self.next_proposal_id;
+        self.next_proposal_id += 1;
+        self.proposals.insert(
+            &id,
+            &Proposal {
+                proposer_id: env::predecessor_account_id(),
+                description,
+                snapshot_at: now.into(),
+                deadline,
+                votes_for: 0.into(),
+                votes_against: 0.into(),
+            },
+        );
+        self.open_proposals.push((id, deadline.0));
+
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        id.into()
+    }
+
+    /// Votes on a proposal with the caller's balance at its snapshot and returns that weight.
+    /// Each account votes once. The caller pays for the storage of the vote record.
+    #[payable]
+    pub fn vote(&mut self, proposal_id: U64, support: bool) -> U128 {
+        let initial_storage_usage = env::storage_usage();
+        let voter_id = env::predecessor_account_id();
+        let mut proposal = self.proposals.get(&proposal_id.0).expect("Proposal not found");
+        assert!(env::block_timestamp() <= proposal.deadline.0, "Voting has ended");
+        self.assert_no_supply_split();
+        let weight = self.internal_snapshot_balance(&voter_id, proposal_id.0);
+        assert!(weight > 0, "No voting power at the snapshot");
+        assert!(self.proposal_votes.insert(&(proposal_id.0, voter_id)), "Already voted");
+
+        if support {
+            proposal.votes_for = (proposal.votes_for.0 + weight).into();
+        } else {
+            proposal.votes_against = (proposal.votes_against.0 + weight).into();
+        }
+        self.proposals.insert(&proposal_id.0, &proposal);
+
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        weight.into()
+    }
+
+    /// Returns the proposal with its current tally.
+    pub fn proposal_result(&self, proposal_id: U64) -> Option<Proposal> {
+        self.proposals.get(&proposal_id.0)
+    }
+
+    /// Returns the balance the account held when the proposal `snapshot_id` was created, or
+    /// `None` if there is no such proposal or voting on it has ended, as the snapshot is only
+    /// recorded while it is open.
+    pub fn balance_of_at(&self, account_id: ValidAccountId, snapshot_id: U64) -> Option<U128> {
+        let now = env::block_timestamp();
+        if !self
+            .open_proposals
+            .iter()
+            .any(|(id, open_until)| *id == snapshot_id.0 && now <= *open_until)
+        {
+            return None;
+        }
+        Some(self.internal_snapshot_balance(account_id.as_ref(), snapshot_id.0).into())
+    }
+}
+
+impl Contract {
+    /// Balance of `account_id` at the snapshot of the open proposal `proposal_id`.
+    fn internal_snapshot_balance(&self, account_id: &AccountId, proposal_id: u64) -> Balance {
+        self.snapshot_balances
+            .get(account_id)
+            .and_then(|saved| saved.into_iter().find(|(id, _)| *id == proposal_id))
+            .map(|(_, balance)| balance)
+            .unwrap_or_else(|| self.token.accounts.get(account_id).unwrap_or(0))
+    }
+
+    /// Saves `previous_balance`, the balance of `account_id` before a change, for the open
+    /// proposals that have none saved for it yet, and drops the balances of ended proposals.
+    pub(crate) fn internal_record_snapshot_balance(
+        &mut self,
+        account_id: &AccountId,
+        previous_balance: Balance,
+    ) {
+        let now = env::block_timestamp();
+        if self.open_proposals.iter().all(|(_, open_until)| now > *open_until) {
+            return;
+        }
+        let saved = self.snapshot_balances.get(account_id).unwrap_or_default();
+        let balances: Vec<(u64, Balance)> = self
+            .open_proposals
+            .iter()
+            .filter(|(_, open_until)| now <= *open_until)
+            .map(|(id, _)| {
+                let balance = saved
+                    .iter()
+                    .find(|(saved_id, _)| saved_id == id)
+                    .map_or(previous_balance, |(_, balance)| *balance);
+                (*id, balance)
+            })
+            .collect();
+        if balances != saved {
+            self.snapshot_balances.insert(account_id, &balances);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract, U64) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(100)
+            .build());
+        contract.ft_transfer(accounts(1), U128(1_000), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .block_timestamp(200)
+            .build());
+        let id = contract.create_proposal("Raise the cap".to_string(), U64(1_000));
+
+        // Moves after the snapshot do not change the voting weights.
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .block_timestamp(300)
+            .build());
+        contract.ft_transfer(accounts(1), U128(5_000), None);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        (context, contract, id)
+    }
+
+    #[test]
+    fn test_votes_weighted_by_snapshot() {
+        let (mut context, mut contract, id) = setup();
+        assert_eq!(contract.vote(id, false).0, TOTAL_SUPPLY - 1_000);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        assert_eq!(contract.vote(id, true).0, 1_000);
+
+        let result = contract.proposal_result(id).unwrap();
+        assert_eq!(result.votes_for.0, 1_000);
+        assert_eq!(result.votes_against.0, TOTAL_SUPPLY - 1_000);
+        assert_eq!(contract.balance_of_at(accounts(1), id), Some(U128(1_000)));
+        assert_eq!(contract.balance_of_at(accounts(3), id), Some(U128(0)));
+    }
+
+    #[test]
+    fn test_transfers_after_snapshot_keep_the_voting_weight() {
+        let (mut context, mut contract, id) = setup();
+        for timestamp in 400..410 {
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(1)
+                .predecessor_account_id(accounts(1))
+                .block_timestamp(timestamp)
+                .build());
+            contract.ft_transfer(accounts(3), U128(1), None);
+        }
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        assert_eq!(contract.vote(id, true).0, 1_000);
+        assert_eq!(contract.balance_of_at(accounts(3), id), Some(U128(0)));
+    }
+
+    #[test]
+    fn test_snapshot_is_dropped_after_the_deadline() {
+        let (mut context, contract, id) = setup();
+        testing_env!(context.block_timestamp(1_001).build());
+        assert_eq!(contract.balance_of_at(accounts(1), id), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Already voted")]
+    fn test_double_vote() {
+        let (mut context, mut contract, id) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.vote(id, true);
+        contract.vote(id, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Voting has ended")]
+    fn test_vote_after_deadline() {
+        let (mut context, mut contract, id) = setup();
+        testing_env!(context.storage_usage(env::storage_usage()).block_timestamp(1_001).build());
+        contract.vote(id, true);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many open proposals")]
+    fn test_open_proposals_are_capped() {
+        let (_, mut contract, _) = setup();
+        for _ in 0..MAX_OPEN_PROPOSALS {
+            contract.create_proposal("Lower the cap".to_string(), U64(1_000));
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Voluntary time-locks. `hodl_lock` holds the caller's whole spendable balance like a
+//! reservation until the chosen duration has passed, and `hodl_unlock` releases it.
+use crate::*;
+
+/// Tokens an account locked itself out of until `unlocks_at`.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct HodlLock {
+    pub amount: U128,
+    /// Block timestamp in nanoseconds.
+    pub unlocks_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Locks the caller's spendable balance for `duration_seconds` and returns the locked
+    /// amount. The tokens stay in the caller's balance but cannot be spent until unlocked. The
+    /// caller pays for the storage of the lock.
+    #[payable]
+    pub fn hodl_lock(&mut self, duration_seconds: u64) -> U128 {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        assert!(duration_seconds > 0, "Lock duration must be positive");
+        assert!(!self.hodl_locks.contains_key(&account_id), "Tokens are already locked");
+        self.assert_not_frozen(&account_id);
+        let amount = self.internal_spendable_balance(&account_id);
+        assert!(amount > 0, "No spendable balance to lock");
+        let unlocks_at = duration_seconds
+            .checked_mul(1_000_000_000)
+            .and_then(|duration| env::block_timestamp().checked_add(duration))
+            .unwrap_or_else(|| env::panic(b"Lock duration is too long"));
+
+        self.hodl_locks.insert(
+            &account_id,
+            &HodlLock { amount: amount.into(), unlocks_at: unlocks_at.into() },
+        );
+        let reserved = self.reserved.get(&account_id).unwrap_or(0);
+        self.reserved.insert(&account_id, &(reserved + amount));
+
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        amount.into()
+    }
+
+    /// Releases the caller's lock once it has expired and returns the released amount.
+    pub fn hodl_unlock(&mut self) -> U128 {
+        let account_id = env::predecessor_account_id();
+        let lock = self.hodl_locks.get(&account_id).expect("No tokens are locked");
+        assert!(env::block_timestamp() >= lock.unlocks_at.0, "Tokens are still locked");
+        self.hodl_locks.remove(&account_id);
+        self.internal_release_reserved(&account_id, lock.amount.0);
+        lock.amount
+    }
+
+    pub fn hodl_status(&self, account_id: ValidAccountId) -> Option<HodlLock> {
+        self.hodl_locks.get(account_id.as_ref())
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(500), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(1_000_000_000)
+            .build());
+        assert_eq!(contract.hodl_lock(60).0, 500);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_hodl_lock() {
+        let (_context, contract) = setup();
+        let lock = contract.hodl_status(accounts(1)).unwrap();
+        assert_eq!(lock.amount.0, 500);
+        assert_eq!(lock.unlocks_at.0, 61_000_000_000);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 500);
+        assert_eq!(contract.spendable_balance_of(accounts(1)).0, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tokens are still locked")]
+    fn test_hodl_unlock_before_expiry() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .block_timestamp(60_999_999_999)
+            .build());
+        contract.hodl_unlock();
+    }
+
+    #[test]
+    fn test_hodl_unlock_after_expiry() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .block_timestamp(61_000_000_000)
+            .build());
+        assert_eq!(contract.hodl_unlock().0, 500);
+        assert!(contract.hodl_status(accounts(1)).is_none());
+        assert_eq!(contract.spendable_balance_of(accounts(1)).0, 500);
+    }
+}
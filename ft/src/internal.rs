@@ -0,0 +1,513 @@
+use crate::*;
+
+/// Charges the storage used by the call against the attached deposit and refunds the excess
+/// to the predecessor.
+pub(crate) fn refund_deposit(storage_used: StorageUsage) {
+    refund_deposit_with_fee(storage_used, 0);
+}
+
+/// Same as `assert_one_yocto`, for calls that may attach more to pay for storage.
+pub(crate) fn assert_at_least_one_yocto() {
+    assert!(env::attached_deposit() >= 1, "Requires attached deposit of at least 1 yoctoNEAR");
+}
+
+/// Thresholds a transfer already cleared through its own approval flow.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Clearance {
+    None,
+    /// Approved by the owner with `approve_large_transfer`.
+    LargeTransfer,
+    /// Queued with `queue_withdrawal` and waited out the withdrawal delay.
+    Withdrawal,
+}
+
+/// Same as `refund_deposit`, for calls that already took `fee` out of the attached deposit.
+pub(crate) fn refund_deposit_with_fee(storage_used: StorageUsage, fee: Balance) {
+    let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
+    let attached_deposit = env::attached_deposit() - fee;
+
+    assert!(
+        required_cost <= attached_deposit,
+        "Must attach {} yoctoNEAR to cover storage",
+        required_cost
+    );
+
+    let refund = attached_deposit - required_cost;
+    if refund > 1 {
+        Promise::new(env::predecessor_account_id()).transfer(refund);
+    }
+}
+
+impl Contract {
+    /// Adds the storage of the `internal_index_account` records, a TWAB checkpoint with its
+    /// count, a full list of pending operations and the per-account transfer bookkeeping,
+    /// measured with the longest account id, to the storage that registering an account
+    /// requires. Larger checkpoint rings are paid for
+    /// with `extend_balance_history`.
+    pub(crate) fn measure_account_index_storage(&mut self) {
+        let initial_storage_usage = env::storage_usage();
+        let tmp_account_id = "a".repeat(64);
+        self.internal_index_account(&tmp_account_id);
+        self.twab_checkpoints.insert(&(tmp_account_id.clone(), 0), &(0, 0));
+        self.twab_counts.insert(&tmp_account_id, &1);
+        let operations: Vec<PendingOperation> = (0..MAX_PENDING_OPERATIONS)
+            .map(|_| PendingOperation {
+                kind: PendingKind::TransferCall,
+                receiver_id: tmp_account_id.clone(),
+                amount: 0.into(),
+                started_at: 0.into(),
+            })
+            .collect();
+        self.pending_operations.insert(&tmp_account_id, &operations);
+        let snapshot_balances: Vec<(u64, Balance)> = vec![(0, 0); MAX_OPEN_PROPOSALS];
+        self.snapshot_balances.insert(&tmp_account_id, &snapshot_balances);
+        self.transfer_counts.insert(&tmp_account_id, &(0, 0));
+        self.block_transfers.insert(&tmp_account_id, &(0, 0));
+        self.interaction_blocks.insert(&tmp_account_id, &(Some(0), Some(0)));
+        self.token.account_storage_usage += env::storage_usage() - initial_storage_usage;
+        self.registered_accounts.remove(&tmp_account_id);
+        self.registered_at.remove(&tmp_account_id);
+        self.pending_operations.remove(&tmp_account_id);
+        self.internal_clear_account_records(&tmp_account_id);
+    }
+
+    /// Removes the TWAB checkpoints, snapshot balances, transfer bookkeeping and storage deposit
+    /// record of a closed account, whose storage the registration deposit paid for.
+    pub(crate) fn internal_clear_account_records(&mut self, account_id: &AccountId) {
+        self.internal_clear_twab(account_id);
+        self.snapshot_balances.remove(account_id);
+        self.transfer_counts.remove(account_id);
+        self.block_transfers.remove(account_id);
+        self.interaction_blocks.remove(account_id);
+        self.storage_deposits.remove(account_id);
+    }
+
+    /// Storage deposit `account_id` paid, which closing the account refunds.
+    pub(crate) fn internal_storage_deposit_of(&self, account_id: &AccountId) -> Balance {
+        self.storage_deposits
+            .get(account_id)
+            .unwrap_or_else(|| self.token.storage_balance_bounds().min.0)
+    }
+
+    /// Adds a newly created balance entry to the account index and records when it was
+    /// created. Does nothing for accounts that are already indexed.
+    pub(crate) fn internal_index_account(&mut self, account_id: &AccountId) {
+        if self.registered_accounts.insert(account_id) {
+            self.registered_at.insert(account_id, &env::block_timestamp());
+        }
+    }
+
+    /// Keeps `holder_count` in sync when a balance goes from `old_balance` to `new_balance`.
+    pub(crate) fn internal_track_holder(&mut self, old_balance: Balance, new_balance: Balance) {
+        if old_balance == 0 && new_balance > 0 {
+            self.holder_count += 1;
+        } else if old_balance > 0 && new_balance == 0 {
+            self.holder_count -= 1;
+        }
+    }
+
+    /// Registers `account_id` with a zero balance and adds it to the account index.
+    pub(crate) fn internal_register_account(&mut self, account_id: &AccountId) {
+        self.token.internal_register_account(account_id);
+        self.internal_index_account(account_id);
+    }
+
+    /// Checks the deposit of `ft_transfer` and `ft_transfer_call`. With
+    /// `auto_register_on_receive`, a transfer to an unregistered receiver registers it and
+    /// pays the storage from the deposit on top of the 1 yoctoNEAR; the rest is refunded.
+    pub(crate) fn internal_transfer_deposit(&mut self, receiver_id: &AccountId) {
+        if !self.auto_register_on_receive || self.token.accounts.contains_key(receiver_id) {
+            assert_one_yocto();
+            return;
+        }
+        let required =
+            Balance::from(self.token.account_storage_usage) * env::storage_byte_cost() + 1;
+        let attached_deposit = env::attached_deposit();
+        assert!(
+            attached_deposit >= required,
+            "Must attach {} yoctoNEAR to register the receiver",
+            required
+        );
+        self.internal_register_account(receiver_id);
+        let refund = attached_deposit - required;
+        if refund > 0 {
+            Promise::new(env::predecessor_account_id()).transfer(refund);
+        }
+    }
+
+    /// Recomputes the total supply from every registered balance and panics if it differs
+    /// from `total_supply`.
+    #[cfg(feature = "debug-assertions")]
+    pub(crate) fn assert_supply_invariant(&self) {
+        let sum: Balance = self
+            .registered_accounts
+            .iter()
+            .map(|account_id| self.token.accounts.get(&account_id).unwrap_or(0))
+            .sum();
+        assert_eq!(sum, self.token.total_supply, "Sum of balances does not match the total supply");
+    }
+
+    /// Forwards the mint fee from the attached deposit to the treasury and returns it. System
+    /// accounts mint without a fee.
+    pub(crate) fn internal_collect_mint_fee(&mut self) -> Balance {
+        if self.internal_is_system_account(&env::predecessor_account_id()) {
+            return 0;
+        }
+        let fee = self.mint_fee_yocto;
+        assert!(env::attached_deposit() >= fee, "Must attach a mint fee of {} yoctoNEAR", fee);
+        if fee > 0 {
+            Promise::new(self.treasury_id.clone()).transfer(fee);
+            self.total_fees_collected += fee;
+        }
+        fee
+    }
+
+    /// Credits newly minted tokens to `receiver_id`, creating its balance entry if needed.
+    pub(crate) fn internal_mint(&mut self, receiver_id: &AccountId, amount: Balance) {
+        self.internal_mint_by(&env::predecessor_account_id(), receiver_id, amount);
+    }
+
+    /// Same as `internal_mint`, for mints requested by `minter_id` rather than the predecessor.
+    pub(crate) fn internal_mint_by(
+        &mut self,
+        minter_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) {
+        assert!(!self.supply_locked, "Total supply is locked");
+        assert!(!self.minting_paused, "Minting is paused");
+        self.assert_no_supply_split();
+        assert!(amount <= 1000, "Cannot mint more than 1000 tokens");
+        if self.whole_units_only {
+            let decimals = self.metadata.get().unwrap().decimals;
+            let is_whole =
+                10u128.checked_pow(decimals as u32).map_or(amount == 0, |unit| amount % unit == 0);
+            assert!(is_whole, "Can only mint whole tokens");
+        }
+        if self.mint_to_self_only {
+            assert_eq!(receiver_id, minter_id, "Can only mint to the predecessor account");
+        }
+
+        let mut amount_for_account = self.token.accounts.get(receiver_id).unwrap_or(0);
+        let is_new_holder = amount_for_account == 0 && amount > 0;
+        self.internal_track_holder(amount_for_account, amount_for_account + amount);
+        amount_for_account += amount;
+
+        self.token.accounts.insert(receiver_id, &amount_for_account);
+        self.internal_index_account(receiver_id);
+        self.token.total_supply = self
+            .token
+            .total_supply
+            .checked_add(amount)
+            .unwrap_or_else(|| env::panic(b"Total supply overflow"));
+        self.internal_update_twab(receiver_id);
+        self.total_minted += amount;
+        let minted_to = self.minted_to.get(receiver_id).unwrap_or(0);
+        self.minted_to.insert(receiver_id, &(minted_to + amount));
+        self.minters.insert(minter_id);
+        self.emit_event(Nep141Event::FtMint(vec![FtMint {
+            owner_id: receiver_id,
+            amount: amount.into(),
+            memo: None,
+        }]));
+        if is_new_holder {
+            if let Some(registry_id) = &self.registry_contract {
+                ext_holder_registry::on_holder_added(
+                    receiver_id.clone(),
+                    registry_id,
+                    NO_DEPOSIT,
+                    GAS_FOR_REGISTRY_NOTIFICATION,
+                );
+            }
+        }
+    }
+
+    /// Removes `amount` from the balance of `account_id` and from the total supply. Panics if
+    /// the total supply would drop below `min_total_supply`.
+    pub(crate) fn internal_burn(
+        &mut self,
+        account_id: &AccountId,
+        amount: Balance,
+        memo: Option<&str>,
+    ) {
+        self.assert_no_supply_split();
+        let balance = self.token.internal_unwrap_balance_of(account_id);
+        assert!(amount <= balance, "The account doesn't have enough balance");
+        if let Some(min_total_supply) = self.min_total_supply {
+            assert!(
+                self.token.total_supply - amount >= min_total_supply,
+                "Burn would drop the total supply below the minimum"
+            );
+        }
+        self.token.accounts.insert(account_id, &(balance - amount));
+        self.internal_track_holder(balance, balance - amount);
+        self.token.total_supply -= amount;
+        self.internal_update_twab(account_id);
+        self.emit_event(Nep141Event::FtBurn(vec![FtBurn {
+            owner_id: account_id,
+            amount: amount.into(),
+            memo,
+        }]));
+        self.internal_notify_burn(account_id, amount);
+    }
+
+    /// Tells the burn listener, if any, about a burn without waiting for the result.
+    pub(crate) fn internal_notify_burn(&self, account_id: &AccountId, amount: Balance) {
+        if let Some(listener_id) = &self.burn_listener {
+            ext_burn_listener::on_burn(
+                account_id.clone(),
+                amount.into(),
+                listener_id,
+                NO_DEPOSIT,
+                GAS_FOR_BURN_NOTIFICATION,
+            );
+        }
+    }
+
+    /// Part of the balance the account can move right now.
+    pub(crate) fn internal_spendable_balance(&self, account_id: &AccountId) -> Balance {
+        let balance = self.token.accounts.get(account_id).unwrap_or(0);
+        balance.saturating_sub(self.reserved.get(account_id).unwrap_or(0))
+    }
+
+    pub(crate) fn assert_spendable(&self, account_id: &AccountId, amount: Balance) {
+        self.check_spendable(account_id, amount)
+            .unwrap_or_else(|error| env::panic(error.as_bytes()));
+    }
+
+    pub(crate) fn check_spendable(
+        &self,
+        account_id: &AccountId,
+        amount: Balance,
+    ) -> Result<(), String> {
+        if amount > self.internal_spendable_balance(account_id) {
+            return Err("Not enough spendable balance".to_string());
+        }
+        Ok(())
+    }
+
+    /// Rejects senders registered less than `min_account_age_seconds` ago. The owner, the
+    /// treasury and system accounts are exempt.
+    fn check_account_age(&self, sender_id: &AccountId) -> Result<(), String> {
+        if let Some(min_age_seconds) = self.min_account_age_seconds {
+            if sender_id == &self.owner_id
+                || sender_id == &self.treasury_id
+                || self.internal_is_system_account(sender_id)
+            {
+                return Ok(());
+            }
+            let registered_at = self.registered_at.get(sender_id).unwrap_or(0);
+            if env::block_timestamp().saturating_sub(registered_at)
+                < min_age_seconds * 1_000_000_000
+            {
+                return Err(format!("Account {} is too new to transfer", sender_id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Rejects transfers that would leave the sender with a nonzero balance under
+    /// `min_account_balance`. Sending everything is always allowed.
+    fn check_no_dust_left(&self, sender_id: &AccountId, amount: Balance) -> Result<(), String> {
+        if self.internal_is_system_account(sender_id) {
+            return Ok(());
+        }
+        if let Some(min_balance) = self.min_account_balance {
+            let remaining = self.token.accounts.get(sender_id).unwrap_or(0).saturating_sub(amount);
+            if remaining != 0 && remaining < min_balance {
+                return Err("Transfer would leave a balance below the minimum".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Number of transfers `sender_id` has sent in this block, counting one more.
+    fn internal_next_block_transfer_count(&self, sender_id: &AccountId) -> u32 {
+        match self.block_transfers.get(sender_id) {
+            Some((last_block, count)) if last_block == env::block_index() => count + 1,
+            _ => 1,
+        }
+    }
+
+    /// Rejects a transfer over `max_transfers_per_block`.
+    fn check_block_transfer(&self, sender_id: &AccountId) -> Result<(), String> {
+        if self.internal_is_system_account(sender_id) {
+            return Ok(());
+        }
+        match self.max_transfers_per_block {
+            Some(max_transfers)
+                if self.internal_next_block_transfer_count(sender_id) > max_transfers =>
+            {
+                Err("Too many transfers in this block".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts an outgoing transfer against `max_transfers_per_block`.
+    fn internal_record_block_transfer(&mut self, sender_id: &AccountId) {
+        if self.internal_is_system_account(sender_id) || self.max_transfers_per_block.is_none() {
+            return;
+        }
+        let count = self.internal_next_block_transfer_count(sender_id);
+        self.block_transfers.insert(sender_id, &(env::block_index(), count));
+    }
+
+    /// With `same_block_guard` on, rejects a sender that received tokens in this block and a
+    /// receiver that sent tokens in it. System accounts are skipped.
+    fn check_block_interaction(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) -> Result<(), String> {
+        if !self.same_block_guard {
+            return Ok(());
+        }
+        let block_index = Some(env::block_index());
+        let (received_at, _) = self.interaction_blocks.get(sender_id).unwrap_or((None, None));
+        let (_, sent_at) = self.interaction_blocks.get(receiver_id).unwrap_or((None, None));
+        if (!self.internal_is_system_account(sender_id) && received_at == block_index)
+            || (!self.internal_is_system_account(receiver_id) && sent_at == block_index)
+        {
+            return Err("Cannot send and receive in the same block".to_string());
+        }
+        Ok(())
+    }
+
+    /// Records the blocks checked by `check_block_interaction`.
+    fn internal_record_block_interaction(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+    ) {
+        if !self.same_block_guard {
+            return;
+        }
+        let block_index = env::block_index();
+        if !self.internal_is_system_account(sender_id) {
+            let (received_at, _) = self.interaction_blocks.get(sender_id).unwrap_or((None, None));
+            self.interaction_blocks.insert(sender_id, &(received_at, Some(block_index)));
+        }
+        if !self.internal_is_system_account(receiver_id) {
+            let (_, sent_at) = self.interaction_blocks.get(receiver_id).unwrap_or((None, None));
+            self.interaction_blocks.insert(receiver_id, &(Some(block_index), sent_at));
+        }
+    }
+
+    /// Transfer path shared by every user-initiated transfer. Disabled while a compliance
+    /// contract is set, since `ft_transfer` then checks transfers asynchronously.
+    pub(crate) fn internal_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) {
+        self.internal_cleared_transfer(sender_id, receiver_id, amount, memo, Clearance::None);
+    }
+
+    /// Same as `internal_transfer` for a transfer that already cleared `clearance`.
+    pub(crate) fn internal_cleared_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+        clearance: Clearance,
+    ) {
+        self.assert_no_compliance_contract();
+        self.assert_memo_allowed(receiver_id, &memo);
+        self.internal_check_transfer(sender_id, receiver_id, amount, clearance);
+        self.internal_transfer_unguarded(sender_id, receiver_id, amount, memo);
+    }
+
+    /// Runs the guards of `internal_transfer` and counts the transfer against the per-block
+    /// limit, without moving any tokens.
+    pub(crate) fn internal_check_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        clearance: Clearance,
+    ) {
+        self.check_transfer(sender_id, receiver_id, amount, clearance)
+            .unwrap_or_else(|error| env::panic(error.as_bytes()));
+        self.internal_record_block_transfer(sender_id);
+        self.internal_record_block_interaction(sender_id, receiver_id);
+    }
+
+    /// The guards of `internal_check_transfer`, without counting the transfer.
+    pub(crate) fn check_transfer(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        clearance: Clearance,
+    ) -> Result<(), String> {
+        if self.reject_self_transfers && sender_id == receiver_id {
+            return Err("Cannot transfer to self".to_string());
+        }
+        self.check_no_supply_split()?;
+        self.check_no_blackout(sender_id)?;
+        self.check_not_frozen(sender_id)?;
+        self.check_not_frozen(receiver_id)?;
+        self.check_account_age(sender_id)?;
+        self.check_transfer_allowed(sender_id, receiver_id)?;
+        self.check_spendable(sender_id, amount)?;
+        if clearance == Clearance::None {
+            self.check_below_large_transfer_threshold(sender_id, amount)?;
+        }
+        if clearance != Clearance::Withdrawal {
+            self.check_below_withdrawal_threshold(sender_id, amount)?;
+        }
+        self.check_no_dust_left(sender_id, amount)?;
+        self.check_block_transfer(sender_id)?;
+        self.check_block_interaction(sender_id, receiver_id)
+    }
+
+    /// Moves tokens without the spendable-balance guard, for callers that already released
+    /// the encumbrance themselves. Blackout windows still apply.
+    pub(crate) fn internal_transfer_unguarded(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) {
+        self.internal_transfer_without_event(sender_id, receiver_id, amount, memo.clone());
+        self.emit_event(Nep141Event::FtTransfer(vec![FtTransfer {
+            old_owner_id: sender_id,
+            new_owner_id: receiver_id,
+            amount: amount.into(),
+            memo: memo.as_deref(),
+        }]));
+    }
+
+    /// Same as `internal_transfer_unguarded` without the NEP-297 event, for batches that log
+    /// one combined event for all of their transfers.
+    pub(crate) fn internal_transfer_without_event(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        amount: Balance,
+        memo: Option<String>,
+    ) {
+        self.assert_no_blackout(sender_id);
+        if sender_id == receiver_id {
+            // Allowed with `set_reject_self_transfers(false)`. The balances stay the same, and
+            // the standard would reject the transfer.
+            return;
+        }
+        let sender_balance = self.token.accounts.get(sender_id).unwrap_or(0);
+        let receiver_balance = self.token.accounts.get(receiver_id).unwrap_or(0);
+        self.token.internal_transfer(sender_id, receiver_id, amount, memo);
+        self.internal_track_holder(sender_balance, sender_balance - amount);
+        self.internal_track_holder(receiver_balance, receiver_balance + amount);
+        self.internal_update_twab(sender_id);
+        self.internal_update_twab(receiver_id);
+        let (sent, received) = self.transfer_counts.get(sender_id).unwrap_or((0, 0));
+        self.transfer_counts.insert(sender_id, &(sent + 1, received));
+        let (sent, received) = self.transfer_counts.get(receiver_id).unwrap_or((0, 0));
+        self.transfer_counts.insert(receiver_id, &(sent, received + 1));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::*;
+
+/// How long a burn request can be confirmed, in nanoseconds.
+pub const BURN_REQUEST_TTL: u64 = 60 * 60 * 1_000_000_000;
+
+/// A burn above `large_burn_threshold` waiting for its requester to confirm it.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BurnRequest {
+    pub account_id: AccountId,
+    pub amount: U128,
+    pub expires_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Records a pending burn of the caller's tokens and returns its id. Nothing is burned
+    /// until `confirm_burn` is called with that id. The caller pays for the storage of the
+    /// request.
+    #[payable]
+    pub fn request_burn(&mut self, amount: U128) -> U64 {
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        let initial_storage_usage = env::storage_usage();
+        let id = self.next_burn_request_id;
+        self.next_burn_request_id += 1;
+        self.burn_requests.insert(
+            &id,
+            &BurnRequest {
+                account_id: env::predecessor_account_id(),
+                amount,
+                expires_at: (env::block_timestamp() + BURN_REQUEST_TTL).into(),
+            },
+        );
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        id.into()
+    }
+
+    /// Burns the tokens of a pending request. The burn whitelist, freezes and reservations
+    /// are checked again at this point.
+    #[payable]
+    pub fn confirm_burn(&mut self, id: U64, memo: Option<String>) {
+        assert_one_yocto();
+        let request = self.burn_requests.get(&id.0).expect("Burn request not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            request.account_id,
+            "Only the requester can confirm the burn"
+        );
+        assert!(env::block_timestamp() < request.expires_at.0, "Burn request expired");
+        self.burn_requests.remove(&id.0);
+        self.internal_checked_burn(request.account_id, request.amount.into(), memo.as_deref());
+    }
+
+    /// Drops a pending burn request of the caller.
+    pub fn cancel_burn(&mut self, id: U64) {
+        let request = self.burn_requests.get(&id.0).expect("Burn request not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            request.account_id,
+            "Only the requester can cancel the burn"
+        );
+        self.burn_requests.remove(&id.0);
+    }
+
+    pub fn get_burn_request(&self, id: U64) -> Option<BurnRequest> {
+        self.burn_requests.get(&id.0)
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_below_large_burn_threshold(&self, amount: Balance) {
+        if let Some(threshold) = self.large_burn_threshold {
+            assert!(amount <= threshold, "Burns above the threshold need a confirmation");
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_large_burn_threshold(Some(U128(1_000)));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_two_phase_burn() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let id = contract.request_burn(U128(5_000));
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        contract.confirm_burn(id, None);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 5_000);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 5_000);
+        assert!(contract.get_burn_request(id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Burns above the threshold need a confirmation")]
+    fn test_large_direct_burn() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_burn(U128(5_000), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Burn request expired")]
+    fn test_expired_burn_request() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let id = contract.request_burn(U128(5_000));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .block_timestamp(BURN_REQUEST_TTL)
+            .build());
+        contract.confirm_burn(id, None);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::*;
+
+/// How long a large transfer request stays valid, in nanoseconds.
+pub const LARGE_TRANSFER_REQUEST_TTL: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// A transfer above `large_transfer_threshold` waiting for the owner's approval.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LargeTransferRequest {
+    pub sender_id: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    pub memo: Option<String>,
+    pub approved: bool,
+    pub expires_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Records a pending transfer above the threshold and returns its id. The caller pays for
+    /// the storage of the request.
+    #[payable]
+    pub fn request_large_transfer(
+        &mut self,
+        receiver_id: ValidAccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) -> U64 {
+        let initial_storage_usage = env::storage_usage();
+        let id = self.next_large_transfer_id;
+        self.next_large_transfer_id += 1;
+        self.large_transfers.insert(
+            &id,
+            &LargeTransferRequest {
+                sender_id: env::predecessor_account_id(),
+                receiver_id: receiver_id.into(),
+                amount,
+                memo,
+                approved: false,
+                expires_at: (env::block_timestamp() + LARGE_TRANSFER_REQUEST_TTL).into(),
+            },
+        );
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        id.into()
+    }
+
+    pub fn approve_large_transfer(&mut self, id: U64) {
+        self.assert_owner();
+        let mut request = self.large_transfers.get(&id.0).expect("Large transfer not found");
+        assert!(env::block_timestamp() < request.expires_at.0, "Large transfer request expired");
+        request.approved = true;
+        self.large_transfers.insert(&id.0, &request);
+    }
+
+    /// Executes an approved request. Other transfer restrictions still apply.
+    #[payable]
+    pub fn execute_large_transfer(&mut self, id: U64) {
+        assert_one_yocto();
+        let request = self.large_transfers.get(&id.0).expect("Large transfer not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            request.sender_id,
+            "Only the sender can execute the transfer"
+        );
+        assert!(request.approved, "Large transfer is not approved");
+        assert!(env::block_timestamp() < request.expires_at.0, "Large transfer request expired");
+        self.large_transfers.remove(&id.0);
+        self.internal_cleared_transfer(
+            &request.sender_id,
+            &request.receiver_id,
+            request.amount.into(),
+            request.memo,
+            Clearance::LargeTransfer,
+        );
+    }
+
+    pub fn get_large_transfer(&self, id: U64) -> Option<LargeTransferRequest> {
+        self.large_transfers.get(&id.0)
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_below_large_transfer_threshold(
+        &self,
+        sender_id: &AccountId,
+        amount: Balance,
+    ) {
+        self.check_below_large_transfer_threshold(sender_id, amount)
+            .unwrap_or_else(|error| env::panic(error.as_bytes()));
+    }
+
+    pub(crate) fn check_below_large_transfer_threshold(
+        &self,
+        sender_id: &AccountId,
+        amount: Balance,
+    ) -> Result<(), String> {
+        if self.internal_is_system_account(sender_id) {
+            return Ok(());
+        }
+        match self.large_transfer_threshold {
+            Some(threshold) if amount > threshold => {
+                Err("Transfers above the threshold need owner approval".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_large_transfer_threshold(Some(U128(1_000)));
+        register_account(&mut context, &mut contract, accounts(1));
+        (context, contract)
+    }
+
+    #[test]
+    fn test_large_transfer_flow() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let id = contract.request_large_transfer(accounts(1), U128(5_000), None);
+        contract.approve_large_transfer(id);
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        contract.execute_large_transfer(id);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 5_000);
+        assert!(contract.get_large_transfer(id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers above the threshold need owner approval")]
+    fn test_large_transfer_without_approval() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(1_000), None);
+        contract.ft_transfer(accounts(1), U128(1_001), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Large transfer is not approved")]
+    fn test_execute_unapproved_large_transfer() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let id = contract.request_large_transfer(accounts(1), U128(5_000), None);
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        contract.execute_large_transfer(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Large transfer request expired")]
+    fn test_execute_expired_large_transfer() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let id = contract.request_large_transfer(accounts(1), U128(5_000), None);
+        contract.approve_large_transfer(id);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .block_timestamp(LARGE_TRANSFER_REQUEST_TTL)
+            .build());
+        contract.execute_large_transfer(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers above the threshold need owner approval")]
+    fn test_reserve_above_threshold() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.reserve(accounts(1), U128(5_000), U64(1_000));
+    }
+}
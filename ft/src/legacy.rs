@@ -0,0 +1,157 @@
+//! Migration from a legacy token. The relayer watches burns on `legacy_contract` and attests
+//! each one with `attest_legacy_burn`; the burner then calls `migrate_from_legacy` with the
+//! attestation nonce as proof and receives the same amount here. Nonces are single-use.
+use crate::*;
+
+/// Longest attestation nonce accepted, in bytes.
+pub const MAX_LEGACY_NONCE_LEN: usize = 128;
+
+/// A legacy burn confirmed by the relayer and not yet migrated.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LegacyAttestation {
+    pub account_id: AccountId,
+    pub amount: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the legacy token and the relayer account that attests its burns. `None` for either
+    /// stops new attestations.
+    pub fn set_legacy_migration(
+        &mut self,
+        legacy_contract: Option<ValidAccountId>,
+        relayer_id: Option<ValidAccountId>,
+    ) {
+        self.assert_owner();
+        self.legacy_contract = legacy_contract.map(|account_id| account_id.into());
+        self.legacy_relayer = relayer_id.map(|account_id| account_id.into());
+    }
+
+    pub fn get_legacy_contract(&self) -> Option<AccountId> {
+        self.legacy_contract.clone()
+    }
+
+    pub fn get_legacy_relayer(&self) -> Option<AccountId> {
+        self.legacy_relayer.clone()
+    }
+
+    /// Records that `account_id` burned `amount` on the legacy contract. Only callable by the
+    /// relayer, which pays for the storage of the attestation.
+    #[payable]
+    pub fn attest_legacy_burn(&mut self, nonce: String, account_id: ValidAccountId, amount: U128) {
+        let initial_storage_usage = env::storage_usage();
+        assert!(self.legacy_contract.is_some(), "Legacy migration is not configured");
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.legacy_relayer,
+            "Can only be called by the legacy relayer"
+        );
+        assert!(nonce.len() <= MAX_LEGACY_NONCE_LEN, "Nonce is too long");
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        assert!(
+            !self.legacy_migrated.contains(&nonce),
+            "Legacy burn {} was already migrated",
+            nonce
+        );
+        assert!(
+            self.legacy_attestations
+                .insert(&nonce, &LegacyAttestation { account_id: account_id.into(), amount })
+                .is_none(),
+            "Legacy burn {} is already attested",
+            nonce
+        );
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+    }
+
+    /// Mints the amount of the caller's attested legacy burn `proof`. The caller pays for the
+    /// storage of the mint.
+    #[payable]
+    pub fn migrate_from_legacy(&mut self, amount: U128, proof: String) {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        assert!(
+            !self.legacy_migrated.contains(&proof),
+            "Legacy burn {} was already migrated",
+            proof
+        );
+        let attestation = self.legacy_attestations.get(&proof).expect("Attestation not found");
+        assert_eq!(attestation.account_id, account_id, "Attestation is for another account");
+        assert_eq!(attestation.amount.0, amount.0, "Amount does not match the attestation");
+
+        self.legacy_attestations.remove(&proof);
+        self.legacy_migrated.insert(&proof);
+        self.internal_mint(&account_id, amount.into());
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+    }
+
+    pub fn get_legacy_attestation(&self, nonce: String) -> Option<LegacyAttestation> {
+        self.legacy_attestations.get(&nonce)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_legacy_migration(Some(accounts(3)), Some(accounts(4)));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.attest_legacy_burn("burn-1".to_string(), accounts(1), U128(250));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.migrate_from_legacy(U128(250), "burn-1".to_string());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_migrate_attested_burn() {
+        let (_context, contract) = setup();
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 250);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 250);
+        assert!(contract.get_legacy_attestation("burn-1".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Legacy burn burn-1 was already migrated")]
+    fn test_migrate_replay() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        contract.migrate_from_legacy(U128(250), "burn-1".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Legacy burn burn-1 was already migrated")]
+    fn test_reattest_migrated_burn() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.attest_legacy_burn("burn-1".to_string(), accounts(1), U128(250));
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only be called by the legacy relayer")]
+    fn test_attest_by_non_relayer() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        contract.attest_legacy_burn("burn-2".to_string(), accounts(1), U128(250));
+    }
+}
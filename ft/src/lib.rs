@@ -16,21 +16,390 @@ NOTES:
     keys on its account.
  */
 use near_contract_standards::fungible_token::FungibleToken;
+use near_contract_standards::fungible_token::core::FungibleTokenCore;
 use near_contract_standards::fungible_token::metadata::{
     FT_METADATA_SPEC, FungibleTokenMetadata, FungibleTokenMetadataProvider,
 };
-use near_sdk::{AccountId, Balance, env, log, near_bindgen, PanicOnDefault, Promise, PromiseOrValue};
+use near_contract_standards::fungible_token::resolver::FungibleTokenResolver;
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
+use near_sdk::{
+    AccountId, assert_one_yocto, Balance, env, ext_contract, Gas, log, near_bindgen,
+    PanicOnDefault, Promise, PromiseOrValue, PromiseResult, StorageUsage,
+};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LazyOption;
-use near_sdk::json_types::{U128, ValidAccountId};
+use near_sdk::collections::{LazyOption, LookupMap, LookupSet, UnorderedSet, Vector};
+use near_sdk::json_types::{Base64VecU8, U128, U64, ValidAccountId};
+use near_sdk::serde::Serialize;
+
+use crate::internal::*;
+use crate::events::*;
+use crate::metadata::{assert_icon_size, DEFAULT_MAX_ICON_BYTES};
+use crate::governance::MAX_OPEN_PROPOSALS;
+use crate::pending::MAX_PENDING_OPERATIONS;
+use crate::reservation::DEFAULT_MAX_LOCKS_PER_ACCOUNT;
+use crate::withdrawal::DEFAULT_WITHDRAWAL_DELAY;
+pub use crate::account_rule::AccountRule;
+pub use crate::blackout::BlackoutWindow;
+pub use crate::config::ConfigPatch;
+pub use crate::governance::Proposal;
+pub use crate::hodl::HodlLock;
+pub use crate::large_burn::BurnRequest;
+pub use crate::large_transfer::LargeTransferRequest;
+pub use crate::legacy::LegacyAttestation;
+pub use crate::memo::MemoEncoding;
+pub use crate::pending::{PendingKind, PendingOperation};
+pub use crate::precision::RemainderPolicy;
+pub use crate::reservation::Reservation;
+pub use crate::simulate::SimResult;
+pub use crate::split::SupplySplit;
+pub use crate::storage_estimate::StorageOp;
+pub use crate::withdrawal::Withdrawal;
+
+mod account_rule;
+mod blackout;
+mod burn;
+mod compliance;
+mod config;
+mod dust;
+mod escrow;
+mod events;
+mod freeze;
+mod governance;
+mod hodl;
+mod internal;
+mod large_burn;
+mod large_transfer;
+mod legacy;
+mod melt;
+mod memo;
+mod metadata;
+mod migration;
+mod mint_registry;
+mod note;
+mod owner;
+mod pending;
+mod precision;
+mod recovery;
+mod reservation;
+mod simulate;
+mod snapshot;
+mod split;
+mod swap;
+mod sponsor;
+mod storage_estimate;
+mod system;
+mod twab;
+mod withdrawal;
+
+const GAS_FOR_RESOLVE_TRANSFER: Gas = 5_000_000_000_000;
+const GAS_FOR_FT_TRANSFER_CALL: Gas = 25_000_000_000_000 + GAS_FOR_RESOLVE_TRANSFER;
+/// Smallest prepaid gas `ft_transfer_call` accepts: its own share plus 10 Tgas for the
+/// receiver's `ft_on_transfer`.
+const MIN_GAS_FOR_FT_TRANSFER_CALL: Gas = GAS_FOR_FT_TRANSFER_CALL + 10_000_000_000_000;
+const GAS_FOR_REGISTRY_NOTIFICATION: Gas = 5_000_000_000_000;
+const GAS_FOR_BURN_NOTIFICATION: Gas = 5_000_000_000_000;
+const NO_DEPOSIT: Balance = 0;
+/// Storage prefix of the balances kept by `FungibleToken`.
+const TOKEN_PREFIX: &[u8] = b"a";
+/// How many processed `ft_mint_idempotent` request ids are remembered before the oldest expire.
+const MAX_MINT_REQUEST_IDS: u64 = 1_000;
+const MAX_MINT_REQUEST_ID_LEN: usize = 64;
 
 near_sdk::setup_alloc!();
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
+    owner_id: AccountId,
+    /// Account that can take over ownership through `complete_emergency_recovery`.
+    recovery_address: Option<AccountId>,
+    /// When the pending recovery was triggered, if one is pending.
+    recovery_started_at: Option<u64>,
+    /// Receives the mint fees. Starts out as the owner.
+    treasury_id: AccountId,
     token: FungibleToken,
+    /// Every account with a balance entry in `token`, so balances can be iterated.
+    registered_accounts: UnorderedSet<AccountId>,
+    /// Block timestamp at which each indexed account was created.
+    registered_at: LookupMap<AccountId, u64>,
+    /// Minimum time since registration before an account can send tokens.
+    min_account_age_seconds: Option<u64>,
+    /// Number of accounts with a nonzero balance.
+    holder_count: u64,
     metadata: LazyOption<FungibleTokenMetadata>,
+    /// Cumulative amount minted through `ft_mint`, excluding the initial supply.
+    total_minted: Balance,
+    /// Cumulative amount minted to each account, excluding the initial supply.
+    minted_to: LookupMap<AccountId, Balance>,
+    /// Distinct accounts that have called `ft_mint`.
+    minters: UnorderedSet<AccountId>,
+    /// When set, `ft_mint` only credits the caller itself.
+    mint_to_self_only: bool,
+    /// When set, mints must be a multiple of `10^decimals`.
+    whole_units_only: bool,
+    reservations: LookupMap<u64, Reservation>,
+    next_reservation_id: u64,
+    /// Ids of the open reservations each account sends.
+    reservation_ids: LookupMap<AccountId, Vec<u64>>,
+    /// Ids of the open reservations each account receives.
+    received_reservation_ids: LookupMap<AccountId, Vec<u64>>,
+    /// Most open reservations one account can send.
+    max_locks_per_account: u32,
+    /// `ft_transfer_call` and `ft_mint_call` operations waiting for their resolve callback.
+    pending_operations: LookupMap<AccountId, Vec<PendingOperation>>,
+    /// Sum of the open reservations made by each account.
+    reserved: LookupMap<AccountId, Balance>,
+    /// Whether mints, transfers and burns log NEP-297 events.
+    emit_events: bool,
+    /// Whether batch methods combine their balance changes into one event.
+    aggregate_batch_events: bool,
+    /// Whether transfers from an account to itself are rejected.
+    reject_self_transfers: bool,
+    /// Account allowed to pay out escrowed tokens.
+    escrow_agent: Option<AccountId>,
+    /// Tokens each depositor holds in escrow on the contract account.
+    escrow_balances: LookupMap<AccountId, Balance>,
+    /// Time-locks set by `hodl_lock`. Locked amounts are also counted in `reserved`.
+    hodl_locks: LookupMap<AccountId, HodlLock>,
+    frozen: UnorderedSet<AccountId>,
+    /// Expiry timestamps of freezes set through `freeze_account_until`.
+    frozen_until: LookupMap<AccountId, u64>,
+    /// The blacklist: frozen accounts without an expiry.
+    blacklisted: UnorderedSet<AccountId>,
+    /// Number of transfers each account has sent and received.
+    transfer_counts: LookupMap<AccountId, (u64, u64)>,
+    /// Cap on transfers an account can send within one block.
+    max_transfers_per_block: Option<u32>,
+    /// Block index of the last outgoing transfer of each account and the transfers sent in it.
+    block_transfers: LookupMap<AccountId, (u64, u32)>,
+    /// Rejects sending and receiving by the same account within one block.
+    same_block_guard: bool,
+    /// Blocks of the last incoming and outgoing transfer of each account, while the guard is on.
+    interaction_blocks: LookupMap<AccountId, (Option<u64>, Option<u64>)>,
+    /// Longest metadata `icon` accepted, in bytes.
+    max_icon_bytes: u32,
+    /// Set by `freeze_reference`; blocks further `set_reference` calls.
+    reference_frozen: bool,
+    minting_paused: bool,
+    /// Set by `lock_supply`; no mint path can run again.
+    supply_locked: bool,
+    /// Smallest nonzero balance a transfer may leave behind on the sender.
+    min_account_balance: Option<Balance>,
+    /// Request ids already handled by `ft_mint_idempotent`.
+    mint_request_ids: LookupSet<String>,
+    /// Ring buffer of the last `MAX_MINT_REQUEST_IDS` request ids, used to expire the oldest.
+    mint_request_log: Vector<String>,
+    mint_request_count: u64,
+    /// Direct transfers above this amount must go through `request_large_transfer`.
+    large_transfer_threshold: Option<Balance>,
+    large_transfers: LookupMap<u64, LargeTransferRequest>,
+    next_large_transfer_id: u64,
+    /// Direct burns above this amount must go through `request_burn` and `confirm_burn`.
+    large_burn_threshold: Option<Balance>,
+    burn_requests: LookupMap<u64, BurnRequest>,
+    next_burn_request_id: u64,
+    /// Floor that burns cannot push the total supply below.
+    min_total_supply: Option<Balance>,
+    /// Lets `ft_transfer` and `ft_transfer_call` register unregistered receivers at the
+    /// sender's expense.
+    auto_register_on_receive: bool,
+    /// Whether `import_balances` is rejected. Only `new_for_import` starts with it unset, until
+    /// `finish_import`.
+    import_finished: bool,
+    /// Decimals that direct transfer amounts are truncated to. `None` keeps full precision.
+    transfer_precision: Option<u8>,
+    remainder_policy: RemainderPolicy,
+    /// Rescaling of all balances started by `split_supply`, if one is in progress.
+    supply_split: Option<SupplySplit>,
+    /// Resolve callbacks with token amounts that have not run yet. Supply split batches wait
+    /// until there are none.
+    pending_callbacks: u64,
+    /// Contract that `ft_transfer` asks through `can_transfer` before moving tokens.
+    compliance_contract: Option<AccountId>,
+    /// Registry that `ft_mint` asks through `has_account` before minting to a receiver.
+    mint_registry: Option<AccountId>,
+    /// Legacy token whose burns `migrate_from_legacy` mints here.
+    legacy_contract: Option<AccountId>,
+    /// Account that attests legacy burns.
+    legacy_relayer: Option<AccountId>,
+    legacy_attestations: LookupMap<String, LegacyAttestation>,
+    /// Nonces of legacy burns that were already migrated.
+    legacy_migrated: LookupSet<String>,
+    /// Accounts that every freeze, fee and limit check lets through.
+    system_accounts: UnorderedSet<AccountId>,
+    /// `(timestamp, balance)` after each balance change, keyed by account and sequence number.
+    twab_checkpoints: LookupMap<(AccountId, u64), (u64, Balance)>,
+    twab_counts: LookupMap<AccountId, u64>,
+    /// Checkpoints kept for the accounts that paid for more than one.
+    twab_capacities: LookupMap<AccountId, u64>,
+    proposals: LookupMap<u64, Proposal>,
+    next_proposal_id: u64,
+    /// `(proposal id, voter)` pairs that already voted.
+    proposal_votes: LookupSet<(u64, AccountId)>,
+    /// `(proposal id, deadline)` of the proposals whose snapshots may still be recorded.
+    open_proposals: Vec<(u64, u64)>,
+    /// Balances saved for the snapshots of open proposals, as `(proposal id, balance)` pairs.
+    snapshot_balances: LookupMap<AccountId, Vec<(u64, Balance)>>,
+    /// When set, only these accounts may call `ft_burn`.
+    burn_allowed: Option<UnorderedSet<AccountId>>,
+    /// NEAR fee each mint must attach on top of its storage cost.
+    mint_fee_yocto: Balance,
+    /// Mint fees forwarded to the treasury so far.
+    total_fees_collected: Balance,
+    /// Payment references left by `ft_transfer_with_note`, keyed by receiver and note key, with
+    /// the sender that left them.
+    notes: LookupMap<(AccountId, String), (AccountId, String)>,
+    /// Owner and treasury transfers above this amount must go through `queue_withdrawal`.
+    withdrawal_threshold: Option<Balance>,
+    /// Time between queueing and executing a withdrawal, in nanoseconds.
+    withdrawal_delay: u64,
+    /// Loosened `withdrawal_threshold` and the block timestamp it applies from.
+    pending_withdrawal_threshold: Option<(Option<Balance>, u64)>,
+    /// Shortened `withdrawal_delay` and the block timestamp it applies from.
+    pending_withdrawal_delay: Option<(u64, u64)>,
+    withdrawals: LookupMap<u64, Withdrawal>,
+    next_withdrawal_id: u64,
+    /// Lowercase substrings that direct transfers reject in memos.
+    banned_memo_terms: Vec<String>,
+    /// Prefixes that `ft_transfer_call` rejects in `msg`.
+    banned_msg_prefixes: Vec<String>,
+    /// Periods set by `add_blackout_window` in which transfers are rejected.
+    blackout_windows: Vec<BlackoutWindow>,
+    /// Memo encodings chosen by receivers. Missing entries mean `MemoEncoding::Utf8`.
+    memo_encodings: LookupMap<AccountId, MemoEncoding>,
+    /// Whether direct transfers must carry a non-empty memo.
+    require_memo: bool,
+    /// Per-sender restrictions on receivers. Missing entries mean `AccountRule::AllowAll`.
+    account_rules: LookupMap<AccountId, AccountRule>,
+    /// Receives `ft_transfer_call` refunds whose sender has been unregistered, instead of
+    /// burning them.
+    fallback_receiver: Option<AccountId>,
+    /// Token contract that `melt` migrates balances to.
+    successor_contract: Option<AccountId>,
+    /// Shortfall below the storage deposit that `storage_deposit` still accepts, covered by
+    /// the contract.
+    storage_deposit_tolerance: Balance,
+    /// Storage deposits of accounts that paid another amount than the current minimum. They
+    /// are refunded instead of the minimum when the account closes.
+    storage_deposits: LookupMap<AccountId, Balance>,
+    /// Cap on the storage cost `register_accounts_bulk` sponsors in total.
+    sponsorship_budget: Option<Balance>,
+    sponsorship_spent: Balance,
+    /// Holder registry notified through `on_holder_added` when a mint creates a new holder.
+    registry_contract: Option<AccountId>,
+    /// Contract notified through `on_burn` whenever tokens are burned.
+    burn_listener: Option<AccountId>,
+}
+
+#[ext_contract(ext_ft_receiver)]
+pub trait FungibleTokenReceiver {
+    fn ft_on_transfer(&mut self, sender_id: AccountId, amount: U128, msg: String)
+        -> PromiseOrValue<U128>;
+}
+
+#[ext_contract(ext_holder_registry)]
+pub trait HolderRegistry {
+    fn on_holder_added(&mut self, account_id: AccountId);
+}
+
+#[ext_contract(ext_burn_listener)]
+pub trait BurnListener {
+    fn on_burn(&mut self, account_id: AccountId, amount: U128);
+}
+
+#[ext_contract(ext_self)]
+trait ExtSelf {
+    fn ft_resolve_transfer(&mut self, sender_id: AccountId, receiver_id: AccountId, amount: U128)
+        -> U128;
+    fn ft_resolve_mint(&mut self, receiver_id: AccountId, amount: U128) -> U128;
+    fn melt_resolve(&mut self, account_id: AccountId, amount: U128) -> bool;
+    fn on_compliance_checked(
+        &mut self,
+        sender_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        remainder: U128,
+        memo: Option<String>,
+    ) -> bool;
+    fn on_mint_destination_checked(
+        &mut self,
+        minter_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        deposit: U128,
+    ) -> bool;
+}
+
+/// Emission statistics returned by `mint_stats`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MintStats {
+    pub total_minted: U128,
+    pub minters_count: u64,
+}
+
+/// Everything that limits what an account can do with its tokens, returned by `account_profile`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AccountProfile {
+    pub balance: U128,
+    /// Part of `balance` held by open reservations.
+    pub reserved: U128,
+    /// Tokens held in escrow for the account; not part of `balance`.
+    pub escrowed: U128,
+    pub frozen: bool,
+    pub spendable: U128,
+}
+
+/// Every owner-settable parameter, returned by `get_config`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Config {
+    pub owner_id: AccountId,
+    pub recovery_address: Option<AccountId>,
+    pub treasury_id: AccountId,
+    pub mint_to_self_only: bool,
+    pub minting_paused: bool,
+    pub supply_locked: bool,
+    pub whole_units_only: bool,
+    pub mint_fee_yocto: U128,
+    pub emit_events: bool,
+    pub aggregate_batch_events: bool,
+    pub reject_self_transfers: bool,
+    pub escrow_agent: Option<AccountId>,
+    pub max_transfers_per_block: Option<u32>,
+    pub max_locks_per_account: u32,
+    pub same_block_guard: bool,
+    pub min_account_balance: Option<U128>,
+    pub min_account_age_seconds: Option<u64>,
+    pub large_transfer_threshold: Option<U128>,
+    pub large_burn_threshold: Option<U128>,
+    pub min_total_supply: Option<U128>,
+    pub withdrawal_threshold: Option<U128>,
+    pub withdrawal_delay: U64,
+    pub max_icon_bytes: u32,
+    pub reference_frozen: bool,
+    pub burn_whitelist_enabled: bool,
+    pub banned_memo_terms: Vec<String>,
+    pub banned_msg_prefixes: Vec<String>,
+    pub blackout_windows: Vec<BlackoutWindow>,
+    pub require_memo: bool,
+    pub registry_contract: Option<AccountId>,
+    pub burn_listener: Option<AccountId>,
+    pub successor_contract: Option<AccountId>,
+    pub fallback_receiver: Option<AccountId>,
+    pub sponsorship_budget: Option<U128>,
+    pub storage_deposit_tolerance: U128,
+    pub auto_register_on_receive: bool,
+    pub transfer_precision: Option<u8>,
+    pub remainder_policy: RemainderPolicy,
+    pub compliance_contract: Option<AccountId>,
+    pub mint_registry: Option<AccountId>,
+    pub legacy_contract: Option<AccountId>,
+    pub legacy_relayer: Option<AccountId>,
+    pub system_accounts: Vec<AccountId>,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAMgAAADICAYAAACtWK6eAAAAGXRFWHRTb2Z0d2FyZQBBZG9iZSBJbWFnZVJlYWR5ccllPAAAAyVpVFh0WE1MOmNvbS5hZG9iZS54bXAAAAAAADw/eHBhY2tldCBiZWdpbj0i77u/IiBpZD0iVzVNME1wQ2VoaUh6cmVTek5UY3prYzlkIj8+IDx4OnhtcG1ldGEgeG1sbnM6eD0iYWRvYmU6bnM6bWV0YS8iIHg6eG1wdGs9IkFkb2JlIFhNUCBDb3JlIDUuNi1jMTQ4IDc5LjE2NDAzNiwgMjAxOS8wOC8xMy0wMTowNjo1NyAgICAgICAgIj4gPHJkZjpSREYgeG1sbnM6cmRmPSJodHRwOi8vd3d3LnczLm9yZy8xOTk5LzAyLzIyLXJkZi1zeW50YXgtbnMjIj4gPHJkZjpEZXNjcmlwdGlvbiByZGY6YWJvdXQ9IiIgeG1sbnM6eG1wPSJodHRwOi8vbnMuYWRvYmUuY29tL3hhcC8xLjAvIiB4bWxuczp4bXBNTT0iaHR0cDovL25zLmFkb2JlLmNvbS94YXAvMS4wL21tLyIgeG1sbnM6c3RSZWY9Imh0dHA6Ly9ucy5hZG9iZS5jb20veGFwLzEuMC9zVHlwZS9SZXNvdXJjZVJlZiMiIHhtcDpDcmVhdG9yVG9vbD0iQWRvYmUgUGhvdG9zaG9wIDIxLjAgKE1hY2ludG9zaCkiIHhtcE1NOkluc3RhbmNlSUQ9InhtcC5paWQ6ODIxMjgwRjk0NTI1MTFFQzlDQkM5RTNGREFGMzFFQkIiIHhtcE1NOkRvY3VtZW50SUQ9InhtcC5kaWQ6ODIxMjgwRkE0NTI1MTFFQzlDQkM5RTNGREFGMzFFQkIiPiA8eG1wTU06RGVyaXZlZEZyb20gc3RSZWY6aW5zdGFuY2VJRD0ieG1wLmlpZDo4MjEyODBGNzQ1MjUxMUVDOUNCQzlFM0ZEQUYzMUVCQiIgc3RSZWY6ZG9jdW1lbnRJRD0ieG1wLmRpZDo4MjEyODBGODQ1MjUxMUVDOUNCQzlFM0ZEQUYzMUVCQiIvPiA8L3JkZjpEZXNjcmlwdGlvbj4gPC9yZGY6UkRGPiA8L3g6eG1wbWV0YT4gPD94cGFja2V0IGVuZD0iciI/PrCWZg4AABhgSURBVHja7F0JlBXVmf4bmsU0NIhsxhARowgIEQERI7ugCajEQQMRQZMZRxOPozM4cUkixiSTTAYy0WFcRsRE0IhxQY1E4SgKKEokiMQNiSAkQgNCszV7z/1Sf52uvq9ev1fv3aq+t97/nfOf0/1qr7rfvf92/1tWW1tLcWLgwIH05ptvUsLooKSPkr5KTlXSQ0kX/v1zvM8RJXuUbFayQckHSt5V8kcl7ynZSwLrgbY1YMCA2M5fnpL31JTJMEzJ2fx3RyWtchzThqW7ktFK0FvsVLJRyVtKXlayRMl6aYqlCdcJ0kvJpUrGKfmygfOVKTmWBSPQVUp2K1mm5HdK5ivZJs1GCGI70Ntfo+SrSlrGfK3WSi5guV3Jb5Xcr+QjaT7pRxPH7neQkueUvKDk6wmQQwfsmJtY/fqVkhOlCQlBbMAXlMxSslTJGAvup1LJ9UreUHKjkmbSlIQgjYVvKnldybcsuN+j2v+dlMxQspDtIYEQJDHAA3Wfkrk8gpjAZ0rmsGG/Po/997FKdwN5HrJBPGJs1fYbSp636x+lSaUMiIPEKWeddVYht3Uqqy+1hgQNfSrbED5mNLB/jZL/VnJalvs7h7w4SdixvxSVKzkgDhJn+7WRIOilNxokB+SgkjO16/RXcjhk34+ZAD46M1HKtOPva+B6cAe3lebrPkFsU7Hgvn3eoErlAz36ddpvb/EoFcQB8mIfr/H/g5W8r2S1kjO0fWeH2CQ+LlLyLHmRe4HYIEYwQsnjMfa8Y7UGi57+N9o+f1KyOGCA38r2xgTy0lDqdV5Msmw4V8mT5AUdBUKQogD1BwG4ygjHVCl5J8L+IMc47benlWwP/L+JvLQT2B/LmTBnckM/EOLReiTHNUGSeVSX/yUQIz2yDfJ58qLS+dgSNaz+XM/HncIGeL62yOKQ6z8U2I48rN389yXafhcrmaL9BqN/Vx7XfVBamhjphRCkuZIXIzTwl0PO8WxEY13P2RoZst8kbZ8pfOxvQ64/L89r/7s0ZzHSo+KHSkZF2B9Zunp6x8OBvzG6/KuS8eTlar0QYqxP0H5botkXMMjnBv5vT14UvxmrXDpmNWCsB/FjVrkEomLlNYIMp3A3ay65WTsP7BbM30AAr2nIdRC82x84fp2SCm2f2wLb/6Bta8GEgZrUOuT8sC9W5nnv72Y5h0BUrIxG9TYVFtNYRZmBuC/meI83aOe4SNsetGU25WjEl7Ja9iUl09hRsD/C/f9cmrUQJBdBbqbiAn/DsryvjqzG6KpjOTdk//jfhRz7+8D2aVnOfwk7CjAnZE+B915DZuauCFJKkC7sWi2GILNC3lXrgKozO4QktwSOh+epq7b9G4HtsClmkjc7sQfbSb6tYSKy/7Q0bSFINoLMMNDAqigzSt1d2+dibfsQrYH/WwjBNoRc6wCZTXvxCThMmrd4sXQgheQqA+cBOS7UfttCXgEGH5O17ZtYvfExUTPqd2dRvZrH8B6Q13WTNG/7kTRBMKfDVCrJJKqfQIgg37PaiNK0geMRIffLYcAm+A/yZikmBeSdnSFNUAji4xglVxg8H4zx07Xf0MjXBozuI4FtJ/E9BHvxnyl5RskKdhyclOD7KOcOQyAE+TuGs2vUFODq/ab228dMnK+wUR7EFMpMWR/Kqlpjzd+AV6xSmqEQBPiHGM6JmISeCAgD/jWqH93+juHRyxROIC+lXlDiBEHkekQM5z2ZvKCdjvOV9CMv5QTzS2ayAX8X1QUNcwGuaOReXcsjEu7/3jyPjYJx0gwtRkJu3nPIXAxBF93zBMP7r1SXxrKZ1augW3hVA+f7G6tn2SZt3Wr4/v9MMkW3YKTFzXtuiP5v0ht0DtsjyAxGFUSkwvseLES8MTEqWGgh2zwOZAufzcY+3MJIjOys7fML8mYZmgLm33eXpl7aKla/GM+NAN9i8hIKR4X0xiezqhUEZi7uDTHwL1PyCf+P5Mf1rKIFkxsPUe6JUlEAb1ZfaYqlSxA02B4JXKMhTA4hw0Ltt0epru4uevTvMfFg4O/T9oVtUmPw/vtIUyxdgnQg8yU6N7CdkS9G8EgSxMPa/5u4J8dItEDJ98lzTS8PMcwRa3nJ4PNI0bkSJsgXycwcCPTii8hLVUFDvi3CsXAFX6r99iITzccPyFsbBPYS8rgeCGz7boia+BuD76gLuVcnWQhiCJ0MGegz2MZ4SMkO8iLlWyIcP1FTxWC8Bz1gx5OXj4Uq7n4xCORhTVfyP5QZtcfEqo2G3lFbkjpaJUsQU5FiPaAGe+GZwP8oEYpiDkhbv4Myy/T0ocwpryhDelizLd4L/H8eeVN4gXXasbsofI56IWhFMtOwZAlSYeg8aNz9td/mscGNdUKQMnI3/zaNPNfv/TmMdcxqfC3wv06CdWyDQP1aGnJPj/FIVCyaUzxZw4IikcQCOi0MnQdxjUlsJ/hAox3CBraOaiX/TF4cw59iexGrfL5q5hePG8L/65NXsG7hoMD/17Fq15nv5WuGOoBmlJ7l8GQEacRrjNdUtv0BcnSj8GLTt/B+QDvKTO2AmuYHEcdQ9tyo23mEeomJCfdvV4ovACooEYIcMHiuE7jX1oF5FZhui7I8enE32CJLAv9foT331oAtg3R4VFG8gY1yFHNAxu3zVDdPvWsM7+0Qi6AECWJ6OeWwrFzkX2G1WizJhqXR9DyqVwJ/n02ZE5WgZvnZv6iDhSUM3mIb5Qm2ceJETWCUE5SYDbLD8PkQ9EP+0odZjOs2bLDfk2W7b8usDPz2qpI1VD+inaTRvNeQsZ8vjg2MkF/g/+HNwwJDCMDCzf0+mc0WEIJkQRUbw6Z0dYwSCPr9JPAbljFAfV9/QlbvEANft2W+x2oNbI6JZH7JhaidyK4EvvVo7hyGkRf3yQaQBd5BBFORgrOsZBmSQLo7PkQ1mU8R13t4v2bVYcqMmt9J4QWlX6V4UvCjyvyYPzNG1OVF3B/m+g+0sf2moexPM6pftM2UnBfyvvqFfMjmTKhai+U/Y1SlZhu6RzhbfkrR5660ZpsP32uI6ZE6DfNBDlFmVNsEJoX8pq8aBXLANdvT8oH87RjOCTsNLukrDZ0P7/IWHu2O07Z1YrXtzIA6i0Dtvfxs8C4uZGcJ/v8/PkZULJ5ReHMMvS6mxOqTmfDhkIGLMj5XU93MQWTfIjt3Vp7n3sAq2BXsFEBF+MUxjR6HyXwpUqTr/yXGEQ/ZB+34Wpg38yn/js7wdfb85ZpBim/zeVGxPIKcG9OHukYzxC/TPgw8VQgMVgR61YYqJUIVvIo9YWE96MIYngHR+hYGydGJR+y41UIkel5b5DmeFIJ4BKmk8LKexcpS7i0R2HuTMpdTCKvhm62R30v1o/SIzOsVU87kXtLkM9xnkBzwFD5lub2ly3AhiIcHKJ4atzUNbK8OMQonU+5i0nP497tDvskrhp/hQoMEmeIYOWrZjVzSRrqPeTGcs4zjItlQSZlrDSItXp9Hclfgb+RjXU5edP2ukHPONXj/G6l+lL8Y4FlvdzDSMDJgz1iHJAmCmIOJaiDQ2aNEnSdpgcLPNN33IJ8PE6UwCWoaeROzplBdGdMg5lP9lXGLwZMGA4Swv05ykCCYkj1WCOLlGhU6TRUN8hFWRzDd9pkIxyI20j+LCuUb9zA4F/D7QNG5RbwNPn8svhmc7beFR6FiAWI+ZPA7Xknu4l/I1tpgCa8PcjxFXzwHhreewfvViOfQVSWQYoW2zwrNKO/IIwq26TWFR5Bdi+icRvGsY1KsLA50RlE8kiVpgxD7y6OuGd40ZAR4OaD+QEX5NXmTo75NXuR4t7a/XiT6SIgtgYJwwfI+U3k0qQpRqZZQ+Iq3UfCEwfc6kOybkXiIYyRTebTMhR9RZuWZkhtBAMzp2BqxJwIZjtHOg8UwMbswLEreh7cFz6Ev/9yF6ueI6SScwK7ji7O8uuuK7F1N1iqeYcFo8Rmr0Rjxkdrjr7WC4O0Oyj8A2dqmEaSxFvG8qYAPoKtZx1NmykMQKDf018Dxvw/Z57HA9n/KMnoRq15YTrqC1bu5bIsU06AuMUiQxy0gyI+5sxqgdWbQUpZRtMTNlqVOkGNYRTHlL+9JmWsW6r084iWnatvHBravpvAKLK352kfZC2eqQf3MIEEWWkCQ/2rg/q6OeK4FbAOWnA3iA431eoo2zRQ9d1juDnr2lTw89wxxo+7kv/15JEEgmc+fTNWbPwyu05W8CP1ktjegbpWR2SLT3yBztbBsmBffkA30MNUvtpELF7CBP6SU3LwUYuj+JML+yI8aH/I7DOkW7GmarjUWLGUQzJS9XPuQMMqDAUxkoD7Px6xi4z+uNc1Bwl8aOtdeCwiyJ0eHeG3E++zBIyNS7NuXIkGIH35BhP31oB9R/em0Iymzssk67aUP1bY/SJlTSyuj6MFFALEL5GK1KvI8Wy0gSK4qkxhBvkXRFiDyU+xfZzdwZakR5BC/tA/y3L8/1a1M6+OpwN/NQtSso9r/kwMqFwz/O6jh1XDjxtVsxI4v4j5eYhf6gUZ8jvfy2GceFRbvgHZwD9utd1LmlOpUuXkpi1u2Kk8DbmbI8XfxtqoQQ3wJZc4jmU52zjL0V9vtG+LWzoVW3DmgOB7KpT7Az/4Xij+IWBVRDfo2u4SLuSYmx02I20i3pZrfau5Bn6HwuRhBwL/+fapfLQWpCkj/QKGBtRrx9Krs7aiu3q5t6M/yU36OD9kWWscjBFSpam7wR9neasKCRvouZc7ehFpyAru9e7OaeTLbQJXswi52PgocJNsi7I9pCB+xDVbI4kGYOvEHVr1KYgTxcR7ll4oyKY9zYbbhUnIv/Tuf1JuN3DiQn3Yre9m6Rfz0HVm9XWHgnsYX2PwwSn6XvZC5roEOAtkHE4OdaKmMID4WsV0wj3u8bMBU2Dnab6exmoE0EkSpr+Ne0mXsZU/cBzyK/JlHlb+xRPVewU2Ownmj2YX6JSo+SfAdKjx5s4ZV5vvYWziA76ktj4p7WCtYxc9elfQHsLFg8hvcwOE7H5RlH3iiugeM+1as197IvY1rhaBreVTYyA1hNT/bWm4UhRZwg9Hfi0fmkUwO03Mv7qTiq0IeZpviLds+jK0NaR1/VCQQfidkewtWsx7jIfcyysy4tRU13PuvYc8PPDPvMzlMVKGE+oFieMP5HfaK0UuHaQKPU4phc0+7j/VTeGJ+HqJyYZ7GTWS24IFpHGYv0oes669h2USZC4OaQhdWUY+J+dk+YjWWhCCNC6zihGmpd7BR6feGNi44s53Voz+xqriGdegka9yu4VEV7y2uYOdWNsy3CEHsADwYCKgh6o2auuMsua+NTAiMcn/kv22Ias9nFRTvy3T0GSMivGZvUwnANWMW9WW/zkY8IrIosPC5BK+PHhNzRJYyKd4n89XrTeEJJjCWoTOVT4a0IORUbaBSgWVxkKhA0AuVPOAGhHs3jrgDZiw+xz1yewc/MUaQH1H+k5ayVZqEs8S61bTSOh8kDpzKRv0jgZ69kCDcVlaX4JtHSnqnlPSFHVg9hbNgTx7vAhH7l1i1bWnrQ5VaoDAKkD6BaDAmNCGodIAN41Xc0yOdAsHDbuzZwQzECqqfoHmYiYSZh+u5pwS5PmGDG+kccB/35N4TxvZO9kLttuQ9IGPgFHZaNJQpe4AbPAJ7qBCJeeyn83v0g4V4vrW8z3K2qXayipZPQHE/H3MwJZ2KcwTpyb36aG4UDU259YspE3u+moSoCOV8jmNZXWuSR0M4yARZyXr+fGq8lZimskQd5Y7yuymn+jGSlvyOu7MnrJBs75U8Ui0SGyQ5FQsN4H/zVA2SFvS2lzfCp7uF7M0XQwrMOWlQsZo4wGHMGFzG3pMKC+8PasoclrYJXfNE8jKabQU8i3dQCpbItp0gUKeeJhvrJWUCowgmbyVRZ3YUJeveLgRfoYYTToUgBhrBr232oIRgGHklgeKO8o904F0g1WWQECQewDuFZbpaOPhOL4hZ/alwqOGNFILEg9tZz3YVSKLsFdO5z3BIdRnsaCdnNUEQt5jieMcDtXBqTOce6pDx68eihCAGMZHs9FZFBRIqO5e42lLOdpkQxBDQM36N0gG4fE1XBkQ2QF/H3sMIIYhZ4/wUSg8GGD4foq7HOvYOBjp4z9YSBBHz41JEkG6Gz+eiVwjftJ8QxJxx2yRFBGlj8FzlZEEx5wIxXAgiyGZTmQJUzx4OE6RMCCKIE+eSnfPw8wEqXJ4oBBHECZej0nDbny0EEcQFTApzPa9plBBEEKeK0iUFKmILIYggLvXK9bkVSDvpJQQRxIGhKXgGTO0dLAQRmAYqsvdLybOMEoIITAPenzYpeRYsDtROCCIwieEpehbn0k6EIHbD5fSSbBgpBBGYAtJLTkvZM7k04UsIYjng9WmesmdCTOckIYjABAam8JlQrqi3EERgAm1S+lyVQhCBCWxL6XPtEIIITGBBSkn/hhBEYAJYuGd2ip4HVfCxVPdWV264XNqg1cCqWVi49FHK7vnBUgbXW2T43k3eQqL6PeJZsLjpKpc+gBDEDSzMsf0HFt0r1nG8Py0vXlQs9zGW7CpFOj5NHa8QxH3YVmgP66X0FIIIbACWGLAthRyjx2ghiMAGYDFOGxcXGisEEdiiXtmY+IeU9q5CEEFjf7vzLb23VuR40WohiPuAIfxli+9vjBBE0JgYTXa7UzHRq70QRCA9dDhAjsFCEEFjAHVuzxISC0EE4RjOhrDtgKFeIQQRSM8cDiRY9hOCCJJEO3Kr0skYIYggScDw7ejQ/SJWUy4EEUiPHA4kL/YWggiSACqCuLaQDopWjxaCCJJAP3KoplQa7BAhiHsNrcxRYncTggjiRDnZm5yYRtVQCOIYerLB6/LoJwQRxAan3aXkrVHYQQgikB44HMeRg0s5CEHcQFfyVmdyHWOFIII4AAO3IgXPgeTF1kIQgahX4UD9rgFCEIEPEzGLDpSuZdjGCEEEPg4bOMdgNnDTAtTxaiYEKQx7DTUqW1At6lUGEM/pIwQpDJ+SQ6Xx88CHRR5fQSkpnxMAkhcvEIIUhs+UvJuixvB6kcfDoO2aQtXTmZwyG22Q51LSCDYrWSbqVSjOIEeSF20kyGM8krgOLHpTzFp8Licn5oKNRbedIQjskLsdbwAgxq+KPEcPlrRCCFIEfqFkpcMf/zYlG4o8R19K9wpgmIbbUghSGODuvVLJdgc//Cwl9xg4z4mUbnQmB+I7NgcK31Eyjrw171zBHCXXGDpX65QTpKmMIMVjqZLzlCy3/D4PKvmhkilkLtC5I+UEwXvaIwQpHlhSGKU2pyr52LJ7q1XyAnnBvDvJW+7YFFaknCDQEKwPCrtiBO5XMl3Jg+TFBiB9WIetJM9tGDewzvc+JTuVbFLyipL5MY5uOP/zZN8inaY6lumGO5SSJkhQ7ZjDgntvz7333ASujaj4ZCZIEurPISUT+Jqd82hMtdxRXMOdRlJYreQpyi8yXsb3uUTJIhcanMtuROiwiFZ/ktD19jSCirdbycyIx1yYMEHQ0KelVQ9MQ7p7M3lX9bAr4ettpxRD5oOkD1tSfj0hiMCpBrtZCCJwCUk32CohiEBGkHDA/V4tBBG4hCSDb7sawSkgBBEUhe0JE0RGEIFT2EZekDEJgBw1QhCBayrW/hSqc0IQgXN2wWYhiMA1HGA1KwlsEYIIXMOhBA11GUEEzhrqSaBKCCIQgmTHdiGIwEUk0bMfSZCIQhCBcwTZLwQRuIokvEsozbRTCCKQESS7nVMjBBG4iB1sI8RNkINCEIGLSCKavq0UXqQQJL0EqRaCCEEE4djN4rojQAgiiAWooRV3EG+zEETgMrY4fn4hiMDpHl5GEIGMIFkAF/IOIYhACBKOXQk4AYQgglgRpxu2mlJerEEIUhoEqY1xBNkjBBG4TpADMZ0by3QfFYIIXAbiIHElE24ulZcoBEkvsBpWXJ6mLUIQgeuAehVX3SoZQQTOI850kyohiCAthnoc2CoEEaQBcdgKtVQC1UyEIEKQYmybbUIQgRAkHPtkBBGkBZ/GcM4dTBIhiMB5bCfz6SbbKb4IvRBEkCiQM7U3BoIcFYII0kKQXTEQhIQggrQQxHRaepUQRJAWHIyBIFuFIII0wXTe1BYhiCBNqLKccEKQmFEmHEi0QYsN4hiOyLtqECaDhfspuRV0hSCG8Aklsy74Jkffj8m8qWohiHtYr+TpBK4z29H3Y9Ko3kslUs0kTQRBKsWNSpbFqMJNVfKqo+9ntZKPDJ3rxYRGa2tQniJD9Hwlk5SMUNKWistBKuPj1yp5VMlrDr8b9PiTlcxUcjp3ilHeDfZHcuICJbdRieH/BRgAm/ILFAHQ8JcAAAAASUVORK5CYII=";
@@ -66,16 +435,160 @@ impl Contract {
     ) -> Self {
         assert!(!env::state_exists(), "Already initialized");
         metadata.assert_valid();
+        assert_icon_size(&metadata.icon, DEFAULT_MAX_ICON_BYTES);
+        let mut this = Self::with_token(
+            owner_id.clone().into(),
+            FungibleToken::new(TOKEN_PREFIX.to_vec()),
+            LazyOption::new(b"m".to_vec(), Some(&metadata)),
+        );
+        this.internal_register_account(owner_id.as_ref());
+        this.token.internal_deposit(owner_id.as_ref(), total_supply.into());
+        this.internal_track_holder(0, total_supply.into());
+        this.internal_update_twab(owner_id.as_ref());
+        this
+    }
+
+    /// Builds the contract around existing balances and metadata, with every other setting at
+    /// its default, and measures the per-account storage of the account index.
+    fn with_token(
+        owner_id: AccountId,
+        token: FungibleToken,
+        metadata: LazyOption<FungibleTokenMetadata>,
+    ) -> Self {
         let mut this = Self {
-            token: FungibleToken::new(b"a".to_vec()),
-            metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)),
+            owner_id: owner_id.clone(),
+            recovery_address: None,
+            recovery_started_at: None,
+            treasury_id: owner_id,
+            token,
+            registered_accounts: UnorderedSet::new(b"g".to_vec()),
+            registered_at: LookupMap::new(b"h".to_vec()),
+            min_account_age_seconds: None,
+            holder_count: 0,
+            metadata,
+            total_minted: 0,
+            minted_to: LookupMap::new(b"t".to_vec()),
+            minters: UnorderedSet::new(b"n".to_vec()),
+            mint_to_self_only: false,
+            whole_units_only: false,
+            reservations: LookupMap::new(b"r".to_vec()),
+            next_reservation_id: 0,
+            reservation_ids: LookupMap::new(b"R".to_vec()),
+            received_reservation_ids: LookupMap::new(b"Q".to_vec()),
+            max_locks_per_account: DEFAULT_MAX_LOCKS_PER_ACCOUNT,
+            pending_operations: LookupMap::new(b"P".to_vec()),
+            reserved: LookupMap::new(b"q".to_vec()),
+            emit_events: true,
+            aggregate_batch_events: false,
+            reject_self_transfers: true,
+            escrow_agent: None,
+            escrow_balances: LookupMap::new(b"e".to_vec()),
+            hodl_locks: LookupMap::new(b"y".to_vec()),
+            frozen: UnorderedSet::new(b"f".to_vec()),
+            frozen_until: LookupMap::new(b"z".to_vec()),
+            blacklisted: UnorderedSet::new(b"B".to_vec()),
+            transfer_counts: LookupMap::new(b"c".to_vec()),
+            max_transfers_per_block: None,
+            block_transfers: LookupMap::new(b"b".to_vec()),
+            same_block_guard: false,
+            interaction_blocks: LookupMap::new(b"I".to_vec()),
+            max_icon_bytes: DEFAULT_MAX_ICON_BYTES,
+            reference_frozen: false,
+            minting_paused: false,
+            supply_locked: false,
+            min_account_balance: None,
+            mint_request_ids: LookupSet::new(b"i".to_vec()),
+            mint_request_log: Vector::new(b"j".to_vec()),
+            mint_request_count: 0,
+            large_transfer_threshold: None,
+            large_transfers: LookupMap::new(b"l".to_vec()),
+            next_large_transfer_id: 0,
+            large_burn_threshold: None,
+            burn_requests: LookupMap::new(b"p".to_vec()),
+            next_burn_request_id: 0,
+            min_total_supply: None,
+            auto_register_on_receive: false,
+            import_finished: true,
+            transfer_precision: None,
+            remainder_policy: RemainderPolicy::Refund,
+            supply_split: None,
+            pending_callbacks: 0,
+            compliance_contract: None,
+            mint_registry: None,
+            legacy_contract: None,
+            legacy_relayer: None,
+            legacy_attestations: LookupMap::new(b"L".to_vec()),
+            legacy_migrated: LookupSet::new(b"M".to_vec()),
+            system_accounts: UnorderedSet::new(b"s".to_vec()),
+            twab_checkpoints: LookupMap::new(b"w".to_vec()),
+            twab_counts: LookupMap::new(b"x".to_vec()),
+            twab_capacities: LookupMap::new(b"H".to_vec()),
+            proposals: LookupMap::new(b"G".to_vec()),
+            next_proposal_id: 0,
+            proposal_votes: LookupSet::new(b"V".to_vec()),
+            open_proposals: Vec::new(),
+            snapshot_balances: LookupMap::new(b"S".to_vec()),
+            burn_allowed: None,
+            mint_fee_yocto: 0,
+            total_fees_collected: 0,
+            notes: LookupMap::new(b"o".to_vec()),
+            withdrawal_threshold: None,
+            withdrawal_delay: DEFAULT_WITHDRAWAL_DELAY,
+            pending_withdrawal_threshold: None,
+            pending_withdrawal_delay: None,
+            withdrawals: LookupMap::new(b"d".to_vec()),
+            next_withdrawal_id: 0,
+            banned_memo_terms: Vec::new(),
+            banned_msg_prefixes: Vec::new(),
+            blackout_windows: Vec::new(),
+            memo_encodings: LookupMap::new(b"v".to_vec()),
+            require_memo: false,
+            account_rules: LookupMap::new(b"k".to_vec()),
+            fallback_receiver: None,
+            successor_contract: None,
+            storage_deposit_tolerance: 0,
+            storage_deposits: LookupMap::new(b"D".to_vec()),
+            sponsorship_budget: None,
+            sponsorship_spent: 0,
+            registry_contract: None,
+            burn_listener: None,
         };
-        this.token.internal_register_account(owner_id.as_ref());
-        this.token.internal_deposit(owner_id.as_ref(), total_supply.into());
+        this.measure_account_index_storage();
         this
     }
 
+    /// Initializes an empty contract with the given fungible token metadata, whose balances
+    /// the owner restores with `import_balances` until `finish_import`.
+    #[init]
+    pub fn new_for_import(owner_id: ValidAccountId, metadata: FungibleTokenMetadata) -> Self {
+        let mut this = Self::new(owner_id, 0.into(), metadata);
+        this.import_finished = false;
+        this
+    }
+
+    /// Same as `new` with the total supply given in whole tokens, which are scaled by
+    /// `10^decimals` of the metadata.
+    #[init]
+    pub fn new_whole_tokens(
+        owner_id: ValidAccountId,
+        whole_supply: U128,
+        metadata: FungibleTokenMetadata,
+    ) -> Self {
+        let total_supply = 10u128
+            .checked_pow(u32::from(metadata.decimals))
+            .and_then(|unit| unit.checked_mul(whole_supply.into()))
+            .unwrap_or_else(|| env::panic(b"Total supply overflow"));
+        Self::new(owner_id, total_supply.into(), metadata)
+    }
+
     fn on_account_closed(&mut self, account_id: AccountId, balance: Balance) {
+        // Removing from the account index moves its last entry, so a split would skip it.
+        self.assert_no_supply_split();
+        self.registered_accounts.remove(&account_id);
+        self.registered_at.remove(&account_id);
+        self.internal_clear_account_records(&account_id);
+        self.internal_clear_holds(&account_id);
+        self.internal_track_holder(balance, 0);
         log!("Closed @{} with {}", account_id, balance);
     }
 
@@ -89,43 +602,460 @@ impl Contract {
         receiver_id: AccountId,
         amount: U128,
     ) {
+        let fee = self.internal_collect_mint_fee();
+        if let Some(registry_id) = self.mint_registry.clone() {
+            let deposit = env::attached_deposit() - fee;
+            self.internal_request_mint_check(&registry_id, receiver_id, amount.into(), deposit);
+            return;
+        }
         //get initial storage usage
+        let initial_storage_usage = env::storage_usage();
+        self.internal_mint(&receiver_id, amount.into());
+
+        //refund any excess storage
+        refund_deposit_with_fee(env::storage_usage() - initial_storage_usage, fee);
+    }
+
+    /// Registers `receiver_id` if needed and mints `amount` to it in one call. The
+    /// registration and the mint are paid from the attached deposit; the excess is refunded.
+    #[payable]
+    pub fn register_and_mint(&mut self, receiver_id: ValidAccountId, amount: U128) {
+        self.assert_no_mint_registry();
+        let fee = self.internal_collect_mint_fee();
+        let initial_storage_usage = env::storage_usage();
+        if !self.token.accounts.contains_key(receiver_id.as_ref()) {
+            self.internal_register_account(receiver_id.as_ref());
+        }
+        self.internal_mint(receiver_id.as_ref(), amount.into());
+        refund_deposit_with_fee(env::storage_usage() - initial_storage_usage, fee);
+    }
+
+    /// Mints `amount` to `receiver_id` and notifies it through `ft_on_transfer`, like
+    /// `ft_transfer_call`. Whatever the receiver returns as unused is burned again in
+    /// `ft_resolve_mint`, unless the burn is blocked, e.g. by the supply floor or a running
+    /// split. The returned tokens then stay minted to the receiver.
+    #[payable]
+    pub fn ft_mint_call(
+        &mut self,
+        receiver_id: ValidAccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.assert_no_mint_registry();
         assert!(
-            amount.0 <= 1000,
-            "Cannot mint more than 1000 tokens"
+            env::prepaid_gas() >= MIN_GAS_FOR_FT_TRANSFER_CALL,
+            "Insufficient gas for mint_call"
+        );
+        let fee = self.internal_collect_mint_fee();
+        let initial_storage_usage = env::storage_usage();
+        self.internal_mint(receiver_id.as_ref(), amount.into());
+        self.internal_add_pending(
+            receiver_id.as_ref(),
+            PendingKind::MintCall,
+            receiver_id.as_ref(),
+            amount.into(),
         );
+        refund_deposit_with_fee(env::storage_usage() - initial_storage_usage, fee);
+        self.pending_callbacks += 1;
+
+        ext_ft_receiver::ft_on_transfer(
+            env::predecessor_account_id(),
+            amount,
+            msg,
+            receiver_id.as_ref(),
+            NO_DEPOSIT,
+            env::prepaid_gas() - GAS_FOR_FT_TRANSFER_CALL,
+        )
+        .then(ext_self::ft_resolve_mint(
+            receiver_id.into(),
+            amount,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_TRANSFER,
+        ))
+        .into()
+    }
+
+    /// Same as `ft_mint`, but a repeated `request_id` is ignored so relayers can retry safely.
+    /// Returns whether the mint was executed. Only the last `MAX_MINT_REQUEST_IDS` ids are kept.
+    #[payable]
+    pub fn ft_mint_idempotent(
+        &mut self,
+        request_id: String,
+        receiver_id: AccountId,
+        amount: U128,
+    ) -> bool {
+        self.assert_no_mint_registry();
+        assert!(request_id.len() <= MAX_MINT_REQUEST_ID_LEN, "Request id is too long");
+        if self.mint_request_ids.contains(&request_id) {
+            log!("Mint request {} was already processed", request_id);
+            if env::attached_deposit() > 0 {
+                Promise::new(env::predecessor_account_id()).transfer(env::attached_deposit());
+            }
+            return false;
+        }
 
+        let fee = self.internal_collect_mint_fee();
         let initial_storage_usage = env::storage_usage();
+        let slot = self.mint_request_count % MAX_MINT_REQUEST_IDS;
+        if self.mint_request_log.len() < MAX_MINT_REQUEST_IDS {
+            self.mint_request_log.push(&request_id);
+        } else {
+            let expired = self.mint_request_log.replace(slot, &request_id);
+            self.mint_request_ids.remove(&expired);
+        }
+        self.mint_request_ids.insert(&request_id);
+        self.mint_request_count += 1;
+        self.internal_mint(&receiver_id, amount.into());
 
-        let mut amount_for_account = self.token.accounts.get(&receiver_id).unwrap_or(0);
-        amount_for_account += amount.0;
+        refund_deposit_with_fee(env::storage_usage() - initial_storage_usage, fee);
+        true
+    }
 
-        self.token.accounts.insert(&receiver_id, &amount_for_account);
-        self.token.total_supply = self
-            .token
-            .total_supply
-            .checked_add(amount.0)
-            .unwrap_or_else(|| env::panic(b"Total supply overflow"));
+    /// Burns the amount the receiver of `ft_mint_call` did not accept and returns the amount
+    /// that stayed minted.
+    #[private]
+    pub fn ft_resolve_mint(&mut self, receiver_id: AccountId, amount: U128) -> U128 {
+        let amount: Balance = amount.into();
+        self.pending_callbacks = self.pending_callbacks.saturating_sub(1);
+        self.internal_remove_pending(&receiver_id, PendingKind::MintCall, &receiver_id, amount);
+        let unused_amount = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(value) => {
+                if let Ok(unused_amount) = near_sdk::serde_json::from_slice::<U128>(&value) {
+                    std::cmp::min(amount, unused_amount.0)
+                } else {
+                    amount
+                }
+            }
+            PromiseResult::Failed => amount,
+        };
+        let spendable = self.internal_spendable_balance(&receiver_id);
+        let burn_amount = std::cmp::min(unused_amount, spendable);
+        if burn_amount == 0 {
+            return amount.into();
+        }
+        if let Err(error) = self.check_refund_burn(&receiver_id, burn_amount) {
+            log!("Returned {} stays minted to @{}: {}", burn_amount, receiver_id, error);
+            return amount.into();
+        }
+        self.internal_burn(&receiver_id, burn_amount, Some("mint refund"));
+        (amount - burn_amount).into()
+    }
 
-        //refund any excess storage
-        let storage_used = env::storage_usage() - initial_storage_usage;
-        let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
-        let attached_deposit = env::attached_deposit();
+    /// Sends the caller's whole spendable balance to `receiver_id` and returns the amount
+    /// sent. Reserved tokens stay with the caller.
+    #[payable]
+    pub fn ft_transfer_all(&mut self, receiver_id: ValidAccountId, memo: Option<String>) -> U128 {
+        assert_one_yocto();
+        let sender_id = env::predecessor_account_id();
+        let spendable = self.internal_spendable_balance(&sender_id);
+        assert!(spendable > 0, "No spendable balance to transfer");
+        let amount = self.internal_truncate_amount(spendable);
+        self.internal_transfer(&sender_id, receiver_id.as_ref(), amount, memo);
+        self.internal_settle_remainder(&sender_id, spendable - amount);
+        amount.into()
+    }
+
+    /// Returns the cumulative emission through `ft_mint` and the number of distinct minters.
+    pub fn mint_stats(&self) -> MintStats {
+        MintStats { total_minted: self.total_minted.into(), minters_count: self.minters.len() }
+    }
+
+    /// Returns how much has been minted to the account in total, as opposed to received
+    /// through transfers.
+    pub fn total_minted_to(&self, account_id: ValidAccountId) -> U128 {
+        self.minted_to.get(account_id.as_ref()).unwrap_or(0).into()
+    }
+
+    /// Returns the NEAR forwarded to the treasury in mint fees since deployment.
+    pub fn total_fees_collected(&self) -> U128 {
+        self.total_fees_collected.into()
+    }
+
+    /// Returns the number of accounts with a nonzero balance. Registered accounts without
+    /// tokens are not counted.
+    pub fn holder_count(&self) -> u64 {
+        self.holder_count
+    }
+
+    pub fn account_profile(&self, account_id: ValidAccountId) -> AccountProfile {
+        let account_id: AccountId = account_id.into();
+        AccountProfile {
+            balance: self.token.accounts.get(&account_id).unwrap_or(0).into(),
+            reserved: self.reserved.get(&account_id).unwrap_or(0).into(),
+            escrowed: self.escrow_balances.get(&account_id).unwrap_or(0).into(),
+            frozen: self.internal_is_frozen(&account_id),
+            spendable: self.internal_spendable_balance(&account_id).into(),
+        }
+    }
+
+    /// Whether the account has reserved, locked or escrowed tokens, is frozen or has an
+    /// account rule.
+    pub fn has_encumbrances(&self, account_id: ValidAccountId) -> bool {
+        let account_id: AccountId = account_id.into();
+        self.reserved.get(&account_id).unwrap_or(0) > 0
+            || self.escrow_balances.get(&account_id).unwrap_or(0) > 0
+            || self.internal_is_frozen(&account_id)
+            || self.account_rules.contains_key(&account_id)
+    }
+
+    /// Returns how many transfers the account has sent and received, in that order.
+    pub fn transfer_counts(&self, account_id: ValidAccountId) -> (u64, u64) {
+        self.transfer_counts.get(account_id.as_ref()).unwrap_or((0, 0))
+    }
+
+    /// Returns the part of `ft_balance_of` the account could transfer right now, after
+    /// subtracting its open reservations.
+    pub fn spendable_balance_of(&self, account_id: ValidAccountId) -> U128 {
+        self.internal_spendable_balance(account_id.as_ref()).into()
+    }
 
+    /// Returns the tokens credited to the contract account itself, such as escrow deposits
+    /// and tokens sent to it by mistake.
+    pub fn contract_token_balance(&self) -> U128 {
+        self.token.accounts.get(&env::current_account_id()).unwrap_or(0).into()
+    }
+
+    pub fn contract_near_balance(&self) -> U128 {
+        env::account_balance().into()
+    }
+
+    /// Returns the contract storage key that holds the balance of `account_id`. Account ids are
+    /// not hashed: the key is the token prefix followed by the Borsh-encoded id.
+    pub fn hashed_account_key(&self, account_id: ValidAccountId) -> Base64VecU8 {
+        let account_id: AccountId = account_id.into();
+        let mut key = TOKEN_PREFIX.to_vec();
+        key.extend(account_id.try_to_vec().unwrap());
+        key.into()
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenCore for Contract {
+    #[payable]
+    fn ft_transfer(&mut self, receiver_id: ValidAccountId, amount: U128, memo: Option<String>) {
+        self.internal_transfer_deposit(receiver_id.as_ref());
+        let sender_id = env::predecessor_account_id();
+        let requested: Balance = amount.into();
+        let amount = self.internal_truncate_amount(requested);
+        if let Some(compliance_id) = self.compliance_contract.clone() {
+            self.internal_request_compliance(
+                &compliance_id,
+                sender_id,
+                receiver_id.into(),
+                amount,
+                requested - amount,
+                memo,
+            );
+        } else {
+            self.internal_transfer(&sender_id, receiver_id.as_ref(), amount, memo);
+            self.internal_settle_remainder(&sender_id, requested - amount);
+        }
+    }
+
+    /// Disabled while a compliance contract is set, since the receiver would be notified
+    /// before the compliance contract answers. Use `ft_transfer` then.
+    #[payable]
+    fn ft_transfer_call(
+        &mut self,
+        receiver_id: ValidAccountId,
+        amount: U128,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        self.internal_transfer_deposit(receiver_id.as_ref());
         assert!(
-            required_cost <= attached_deposit,
-            "Must attach {} yoctoNEAR to cover storage", required_cost
+            env::prepaid_gas() >= MIN_GAS_FOR_FT_TRANSFER_CALL,
+            "Insufficient gas for transfer_call"
+        );
+        let sender_id = env::predecessor_account_id();
+        let requested: Balance = amount.into();
+        let amount = self.internal_truncate_amount(requested);
+        self.assert_msg_allowed(&msg);
+        self.internal_transfer(&sender_id, receiver_id.as_ref(), amount, memo);
+        self.internal_settle_remainder(&sender_id, requested - amount);
+        self.internal_add_pending(
+            &sender_id,
+            PendingKind::TransferCall,
+            receiver_id.as_ref(),
+            amount,
         );
+        self.pending_callbacks += 1;
+        let amount: U128 = amount.into();
+        ext_ft_receiver::ft_on_transfer(
+            sender_id.clone(),
+            amount,
+            msg,
+            receiver_id.as_ref(),
+            NO_DEPOSIT,
+            env::prepaid_gas() - GAS_FOR_FT_TRANSFER_CALL,
+        )
+        .then(ext_self::ft_resolve_transfer(
+            sender_id,
+            receiver_id.into(),
+            amount,
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_TRANSFER,
+        ))
+        .into()
+    }
+
+    fn ft_total_supply(&self) -> U128 {
+        self.token.ft_total_supply()
+    }
 
-        let refund = attached_deposit - required_cost;
-        if refund > 1 {
-            Promise::new(env::predecessor_account_id()).transfer(refund);
+    fn ft_balance_of(&self, account_id: ValidAccountId) -> U128 {
+        self.token.ft_balance_of(account_id)
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenResolver for Contract {
+    #[private]
+    fn ft_resolve_transfer(
+        &mut self,
+        sender_id: ValidAccountId,
+        receiver_id: ValidAccountId,
+        amount: U128,
+    ) -> U128 {
+        let sender_id: AccountId = sender_id.into();
+        self.pending_callbacks = self.pending_callbacks.saturating_sub(1);
+        self.internal_remove_pending(
+            &sender_id,
+            PendingKind::TransferCall,
+            receiver_id.as_ref(),
+            amount.into(),
+        );
+        let sender_balance = self.token.accounts.get(&sender_id).unwrap_or(0);
+        let receiver_balance = self.token.accounts.get(receiver_id.as_ref()).unwrap_or(0);
+        let (used_amount, mut burned_amount) =
+            self.token.internal_ft_resolve_transfer(&sender_id, receiver_id.clone(), amount);
+        let receiver_id: AccountId = receiver_id.into();
+        // The standard reports a burned refund as used, so the refund taken from the receiver
+        // is read from its balance.
+        let refund = receiver_balance - self.token.accounts.get(&receiver_id).unwrap_or(0);
+        if refund > 0 {
+            self.internal_update_twab(&receiver_id);
+            self.internal_track_holder(receiver_balance, receiver_balance - refund);
+        }
+        if burned_amount > 0 {
+            if let Some(fallback_id) = self.fallback_receiver.clone() {
+                if let Some(fallback_balance) = self.token.accounts.get(&fallback_id) {
+                    self.token.internal_deposit(&fallback_id, burned_amount);
+                    self.token.total_supply += burned_amount;
+                    self.internal_update_twab(&fallback_id);
+                    self.internal_track_holder(fallback_balance, fallback_balance + burned_amount);
+                    self.emit_event(Nep141Event::FtTransfer(vec![FtTransfer {
+                        old_owner_id: &receiver_id,
+                        new_owner_id: &fallback_id,
+                        amount: burned_amount.into(),
+                        memo: Some("refund"),
+                    }]));
+                    burned_amount = 0;
+                }
+            }
+            if burned_amount > 0 {
+                self.emit_event(Nep141Event::FtBurn(vec![FtBurn {
+                    owner_id: &receiver_id,
+                    amount: burned_amount.into(),
+                    memo: Some("refund"),
+                }]));
+                self.internal_notify_burn(&sender_id, burned_amount);
+                self.on_tokens_burned(sender_id, burned_amount);
+            }
+        } else if refund > 0 {
+            self.internal_update_twab(&sender_id);
+            self.internal_track_holder(sender_balance, sender_balance + refund);
+            self.emit_event(Nep141Event::FtTransfer(vec![FtTransfer {
+                old_owner_id: &receiver_id,
+                new_owner_id: &sender_id,
+                amount: refund.into(),
+                memo: Some("refund"),
+            }]));
         }
+        used_amount.into()
     }
 }
 
-near_contract_standards::impl_fungible_token_core!(Contract, token, on_tokens_burned);
-near_contract_standards::impl_fungible_token_storage!(Contract, token, on_account_closed);
+#[near_bindgen]
+impl StorageManagement for Contract {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<ValidAccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let registered_id = account_id
+            .as_ref()
+            .map_or_else(env::predecessor_account_id, |account_id| account_id.as_ref().clone());
+        let min_balance = self.token.storage_balance_bounds().min.0;
+        let attached_deposit = env::attached_deposit();
+        if attached_deposit < min_balance
+            && attached_deposit + self.storage_deposit_tolerance >= min_balance
+            && !self.token.accounts.contains_key(&registered_id)
+        {
+            log!(
+                "Registered @{} with {} yoctoNEAR less than the storage deposit",
+                registered_id,
+                min_balance - attached_deposit
+            );
+            self.internal_register_account(&registered_id);
+            self.storage_deposits.insert(&registered_id, &attached_deposit);
+            return StorageBalance { total: attached_deposit.into(), available: 0.into() };
+        }
+        self.token.storage_deposit(account_id, registration_only);
+        self.internal_index_account(&registered_id);
+        let total = self.internal_storage_deposit_of(&registered_id);
+        StorageBalance { total: total.into(), available: 0.into() }
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        self.token.storage_withdraw(amount)
+    }
+
+    /// Same as the standard's `storage_unregister`, but refunds the storage deposit the
+    /// account actually paid, plus the attached yoctoNEAR. Accounts holding escrow cannot
+    /// unregister, even with `force`, since a supply split rescales escrow with the account.
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = match self.token.accounts.get(&account_id) {
+            Some(balance) => balance,
+            None => {
+                log!("The account {} is not registered", &account_id);
+                return false;
+            }
+        };
+        if balance > 0 && !force.unwrap_or(false) {
+            env::panic(b"Can't unregister the account with the positive balance without force")
+        }
+        if self.escrow_balances.get(&account_id).is_some() {
+            env::panic(b"Can't unregister the account with escrowed tokens")
+        }
+        let refund = self.internal_storage_deposit_of(&account_id) + 1;
+        self.token.accounts.remove(&account_id);
+        self.token.total_supply -= balance;
+        Promise::new(account_id.clone()).transfer(refund);
+        self.on_account_closed(account_id, balance);
+        true
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        self.token.storage_balance_bounds()
+    }
+
+    fn storage_balance_of(&self, account_id: ValidAccountId) -> Option<StorageBalance> {
+        if !self.token.accounts.contains_key(account_id.as_ref()) {
+            return None;
+        }
+        let total = self.internal_storage_deposit_of(account_id.as_ref());
+        Some(StorageBalance { total: total.into(), available: 0.into() })
+    }
+}
 
 #[near_bindgen]
 impl FungibleTokenMetadataProvider for Contract {
@@ -138,13 +1068,16 @@ impl FungibleTokenMetadataProvider for Contract {
 mod tests {
     use near_sdk::{Balance, testing_env};
     use near_sdk::MockedBlockchain;
-    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::test_utils::{
+        accounts, get_created_receipts, testing_env_with_promise_results, VMContextBuilder,
+    };
 
     use super::*;
 
-    const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
+    pub(crate) const TOTAL_SUPPLY: Balance = 1_000_000_000_000_000;
+    pub(crate) const MINT_DEPOSIT: Balance = 100_000_000_000_000_000_000_000;
 
-    fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
+    pub(crate) fn get_context(predecessor_account_id: ValidAccountId) -> VMContextBuilder {
         let mut builder = VMContextBuilder::new();
         builder
             .current_account_id(accounts(0))
@@ -153,6 +1086,20 @@ mod tests {
         builder
     }
 
+    /// Pays for the storage registration of `account_id` from its own predecessor context.
+    pub(crate) fn register_account(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        account_id: ValidAccountId,
+    ) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.into())
+            .predecessor_account_id(account_id)
+            .build());
+        contract.storage_deposit(None, None);
+    }
+
     #[test]
     fn test_new() {
         let mut context = get_context(accounts(1));
@@ -163,6 +1110,24 @@ mod tests {
         assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY);
     }
 
+    #[test]
+    fn test_new_whole_tokens() {
+        let context = get_context(accounts(1));
+        testing_env!(context.build());
+        let metadata = FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "BlaBla Token".to_string(),
+            symbol: "BLABLA".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 18,
+        };
+        let contract = Contract::new_whole_tokens(accounts(1), U128(1_000_000), metadata);
+        assert_eq!(contract.ft_total_supply().0, 1_000_000 * 10u128.pow(18));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 1_000_000 * 10u128.pow(18));
+    }
+
     #[test]
     #[should_panic(expected = "The contract is not initialized")]
     fn test_default() {
@@ -201,4 +1166,1049 @@ mod tests {
         assert_eq!(contract.ft_balance_of(accounts(2)).0, (TOTAL_SUPPLY - transfer_amount));
         assert_eq!(contract.ft_balance_of(accounts(1)).0, transfer_amount);
     }
+
+    #[test]
+    fn test_mint_stats() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        assert_eq!(contract.mint_stats().total_minted.0, 0);
+        assert_eq!(contract.mint_stats().minters_count, 0);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(100));
+        contract.ft_mint(accounts(3).into(), U128(200));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.ft_mint(accounts(3).into(), U128(50));
+
+        let stats = contract.mint_stats();
+        assert_eq!(stats.total_minted.0, 350);
+        assert_eq!(stats.minters_count, 2);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 350);
+    }
+
+    #[test]
+    fn test_total_minted_to() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(100));
+        contract.ft_mint(accounts(1).into(), U128(250));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(500), None);
+
+        assert_eq!(contract.total_minted_to(accounts(1)).0, 350);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 850);
+        assert_eq!(contract.total_minted_to(accounts(2)).0, 0);
+    }
+
+    #[test]
+    fn test_contract_balances() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        assert_eq!(contract.contract_token_balance().0, 0);
+        register_account(&mut context, &mut contract, accounts(0));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .account_balance(5 * MINT_DEPOSIT)
+            .build());
+        contract.ft_transfer(accounts(0), U128(700), None);
+        assert_eq!(contract.contract_token_balance().0, 700);
+        assert_eq!(contract.contract_near_balance().0, 5 * MINT_DEPOSIT);
+    }
+
+    #[cfg(feature = "debug-assertions")]
+    #[test]
+    fn test_supply_invariant_holds() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.assert_supply_invariant();
+        register_account(&mut context, &mut contract, accounts(1));
+        contract.assert_supply_invariant();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.ft_mint(accounts(3).into(), U128(400));
+        contract.ft_mint(accounts(1).into(), U128(100));
+        contract.assert_supply_invariant();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(1_000), None);
+        contract.assert_supply_invariant();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_burn(U128(300), None);
+        contract.assert_supply_invariant();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.sweep_dust(vec![accounts(1), accounts(3)], U128(1_000), true);
+        contract.assert_supply_invariant();
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 200);
+    }
+
+    #[test]
+    fn test_refund_to_unregistered_sender_goes_to_fallback() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+        register_account(&mut context, &mut contract, accounts(4));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.set_fallback_receiver(Some(accounts(4)));
+        contract.ft_transfer(accounts(3), U128(100), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.ft_transfer_call(accounts(1), U128(100), None, "".to_string());
+        assert!(contract.storage_unregister(None));
+
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(0)
+                .predecessor_account_id(accounts(0))
+                .build(),
+            PromiseResult::Successful(b"\"100\"".to_vec()),
+        );
+        let holders = contract.holder_count();
+        let used = contract.ft_resolve_transfer(accounts(3), accounts(1), U128(100));
+        assert_eq!(used.0, 100);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 100);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        // accounts(1) stopped holding and accounts(4) started.
+        assert_eq!(contract.holder_count(), holders);
+    }
+
+    #[test]
+    fn test_holder_count() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        assert_eq!(contract.holder_count(), 1);
+        register_account(&mut context, &mut contract, accounts(1));
+        assert_eq!(contract.holder_count(), 1);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        assert_eq!(contract.holder_count(), 2);
+        contract.ft_transfer(accounts(1), U128(100), None);
+        assert_eq!(contract.holder_count(), 2);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_transfer(accounts(2), U128(150), None);
+        assert_eq!(contract.holder_count(), 2);
+        contract.ft_burn(U128(50), None);
+        assert_eq!(contract.holder_count(), 1);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(10));
+        assert_eq!(contract.holder_count(), 2);
+    }
+
+    #[test]
+    fn test_hashed_account_key() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let key = contract.hashed_account_key(accounts(1));
+        assert_eq!(key.0, b"a\x03\x00\x00\x00bob".to_vec());
+
+        register_account(&mut context, &mut contract, accounts(1));
+        assert!(env::storage_has_key(&key.0));
+    }
+
+    #[test]
+    fn test_transfer_counts() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        contract.ft_transfer(accounts(1), U128(100), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_transfer(accounts(2), U128(50), None);
+
+        assert_eq!(contract.transfer_counts(accounts(2)), (2, 1));
+        assert_eq!(contract.transfer_counts(accounts(1)), (1, 2));
+        assert_eq!(contract.transfer_counts(accounts(3)), (0, 0));
+    }
+
+    #[test]
+    fn test_transfer_bookkeeping_is_prepaid() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_max_transfers_per_block(Some(10));
+        contract.set_same_block_guard(true);
+        // Creates the owner's records, which no registration paid for.
+        register_account(&mut context, &mut contract, accounts(3));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(3), U128(100), None);
+
+        let before = env::storage_usage();
+        register_account(&mut context, &mut contract, accounts(1));
+        for block_index in 1..=2 {
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(1)
+                .predecessor_account_id(accounts(2))
+                .block_index(block_index * 2)
+                .build());
+            contract.ft_transfer(accounts(1), U128(100), None);
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .predecessor_account_id(accounts(1))
+                .block_index(block_index * 2 + 1)
+                .build());
+            contract.ft_transfer(accounts(2), U128(50), None);
+        }
+        assert!(env::storage_usage() - before <= contract.token.account_storage_usage);
+    }
+
+    #[test]
+    fn test_max_transfers_per_block_resets_in_next_block() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_max_transfers_per_block(Some(2));
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_index(10)
+            .build());
+        contract.ft_transfer(accounts(1), U128(1), None);
+        contract.ft_transfer(accounts(1), U128(1), None);
+
+        testing_env!(context.storage_usage(env::storage_usage()).block_index(11).build());
+        contract.ft_transfer(accounts(1), U128(1), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot send and receive in the same block")]
+    fn test_same_block_guard_rejects_receive_then_send() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_same_block_guard(true);
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_index(10)
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_transfer(accounts(2), U128(50), None);
+    }
+
+    #[test]
+    fn test_same_block_guard_allows_send_in_next_block() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_same_block_guard(true);
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_index(10)
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .block_index(11)
+            .build());
+        contract.ft_transfer(accounts(2), U128(50), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 50);
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient gas for transfer_call")]
+    fn test_transfer_call_with_low_prepaid_gas() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .prepaid_gas(GAS_FOR_FT_TRANSFER_CALL)
+            .build());
+        contract.ft_transfer_call(accounts(1), U128(100), None, "".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many transfers in this block")]
+    fn test_max_transfers_per_block() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_max_transfers_per_block(Some(2));
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_index(10)
+            .build());
+        contract.ft_transfer(accounts(1), U128(1), None);
+        contract.ft_transfer(accounts(1), U128(1), None);
+        contract.ft_transfer(accounts(1), U128(1), None);
+    }
+
+    #[test]
+    fn test_min_account_balance_allows_full_transfer() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_min_account_balance(Some(U128(100)));
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(TOTAL_SUPPLY - 100), None);
+        contract.ft_transfer(accounts(1), U128(100), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 0);
+    }
+
+    const SECOND: u64 = 1_000_000_000;
+
+    fn setup_min_account_age() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.block_timestamp(100 * SECOND).build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_min_account_age_seconds(Some(60));
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        (context, contract)
+    }
+
+    #[test]
+    #[should_panic(expected = "Account bob is too new to transfer")]
+    fn test_min_account_age_rejects_new_account() {
+        let (mut context, mut contract) = setup_min_account_age();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(130 * SECOND)
+            .build());
+        contract.ft_transfer(accounts(2), U128(10), None);
+    }
+
+    #[test]
+    fn test_min_account_age_allows_aged_account() {
+        let (mut context, mut contract) = setup_min_account_age();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(160 * SECOND)
+            .build());
+        contract.ft_transfer(accounts(2), U128(10), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 90);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfer would leave a balance below the minimum")]
+    fn test_min_account_balance_rejects_dust() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_min_account_balance(Some(U128(100)));
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(TOTAL_SUPPLY - 99), None);
+    }
+
+    #[test]
+    fn test_account_profile() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.reserve(accounts(1), U128(300), U64(1_000));
+        contract.escrow_deposit(U128(200));
+        contract.freeze_account(accounts(2));
+
+        let profile = contract.account_profile(accounts(2));
+        assert_eq!(profile.balance.0, TOTAL_SUPPLY - 200);
+        assert_eq!(profile.reserved.0, 300);
+        assert_eq!(profile.escrowed.0, 200);
+        assert!(profile.frozen);
+        assert_eq!(profile.spendable.0, TOTAL_SUPPLY - 500);
+
+        let empty = contract.account_profile(accounts(3));
+        assert_eq!(empty.balance.0, 0);
+        assert!(!empty.frozen);
+    }
+
+    #[test]
+    fn test_has_encumbrances() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        assert!(!contract.has_encumbrances(accounts(2)));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.reserve(accounts(1), U128(300), U64(1_000));
+        assert!(contract.has_encumbrances(accounts(2)));
+
+        contract.set_account_rule(accounts(3), AccountRule::DenyAll);
+        assert!(contract.has_encumbrances(accounts(3)));
+        assert!(!contract.has_encumbrances(accounts(4)));
+    }
+
+    #[test]
+    fn test_spendable_balance_of() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        assert_eq!(contract.spendable_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+        assert_eq!(contract.spendable_balance_of(accounts(1)).0, 0);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.reserve(accounts(1), U128(1_000), U64(1_000));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+        assert_eq!(contract.spendable_balance_of(accounts(2)).0, TOTAL_SUPPLY - 1_000);
+    }
+
+    #[test]
+    fn test_auto_register_on_receive() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_auto_register_on_receive(true);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+        assert!(contract.storage_balance_of(accounts(1)).is_some());
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(2).as_ref()));
+    }
+
+    #[test]
+    #[should_panic(expected = "to register the receiver")]
+    fn test_auto_register_on_receive_with_low_deposit() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_auto_register_on_receive(true);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+    }
+
+    #[test]
+    fn test_storage_deposit_within_tolerance() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_storage_deposit_tolerance(U128(1_000));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.0 - 1_000)
+            .predecessor_account_id(accounts(1))
+            .build());
+        let deposit = contract.storage_balance_bounds().min.0 - 1_000;
+        assert_eq!(contract.storage_deposit(None, None).total.0, deposit);
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().total.0, deposit);
+    }
+
+    #[test]
+    #[should_panic(expected = "The attached deposit is less than the minimum storage balance")]
+    fn test_storage_deposit_over_tolerance() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_storage_deposit_tolerance(U128(1_000));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(contract.storage_balance_bounds().min.0 - 1_001)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.storage_deposit(None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot transfer to self")]
+    fn test_transfer_to_self() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(2), U128(10), None);
+    }
+
+    #[test]
+    fn test_transfer_to_self_when_allowed() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_reject_self_transfers(false);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(2), U128(10), None);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+        assert!(!contract.get_config().reject_self_transfers);
+    }
+
+    #[test]
+    fn test_transfer_all() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.reserve(accounts(1), U128(1_000), U64(1_000));
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        let sent = contract.ft_transfer_all(accounts(3), None);
+        assert_eq!(sent.0, TOTAL_SUPPLY - 1_000);
+        assert_eq!(contract.spendable_balance_of(accounts(2)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 1_000);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, TOTAL_SUPPLY - 1_000);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.ft_transfer_all(accounts(2), None);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    fn test_mint_call_fully_accepted() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint_call(accounts(3), U128(500), "stake".to_string());
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 500);
+
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(0)
+                .predecessor_account_id(accounts(0))
+                .build(),
+            PromiseResult::Successful(b"\"0\"".to_vec()),
+        );
+        assert_eq!(contract.ft_resolve_mint(accounts(3).into(), U128(500)).0, 500);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 500);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 500);
+    }
+
+    #[test]
+    fn test_mint_call_partial_return_is_burned() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint_call(accounts(3), U128(500), "stake".to_string());
+
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(0)
+                .predecessor_account_id(accounts(0))
+                .build(),
+            PromiseResult::Successful(b"\"200\"".to_vec()),
+        );
+        assert_eq!(contract.ft_resolve_mint(accounts(3).into(), U128(500)).0, 300);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 300);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 300);
+    }
+
+    #[test]
+    fn test_mint_call_return_below_supply_floor_stays_minted() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint_call(accounts(3), U128(500), "stake".to_string());
+        testing_env!(context.attached_deposit(0).build());
+        contract.set_min_total_supply(Some(U128(TOTAL_SUPPLY + 500)));
+
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .predecessor_account_id(accounts(0))
+                .build(),
+            PromiseResult::Successful(b"\"200\"".to_vec()),
+        );
+        assert_eq!(contract.ft_resolve_mint(accounts(3).into(), U128(500)).0, 500);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 500);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 500);
+        assert!(contract.pending_operations(accounts(3)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Insufficient gas for mint_call")]
+    fn test_mint_call_with_low_prepaid_gas() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .prepaid_gas(GAS_FOR_FT_TRANSFER_CALL)
+            .build());
+        contract.ft_mint_call(accounts(3), U128(500), "stake".to_string());
+    }
+
+    #[test]
+    fn test_minting_paused_keeps_transfers_live() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.pause_minting();
+        assert!(contract.is_minting_paused());
+        contract.ft_transfer(accounts(1), U128(10), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+
+        contract.resume_minting();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(10));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 20);
+    }
+
+    #[test]
+    #[should_panic(expected = "Minting is paused")]
+    fn test_mint_while_minting_paused() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.pause_minting();
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(10));
+    }
+
+    #[test]
+    #[should_panic(expected = "Total supply is locked")]
+    fn test_mint_after_lock_supply() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(10));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+
+        contract.lock_supply();
+        assert!(contract.is_supply_locked());
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        contract.ft_mint(accounts(1).into(), U128(10));
+    }
+
+    #[test]
+    fn test_mint_idempotent_ignores_duplicate() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        assert!(contract.ft_mint_idempotent("req-1".to_string(), accounts(1).into(), U128(100)));
+        assert!(!contract.ft_mint_idempotent("req-1".to_string(), accounts(1).into(), U128(100)));
+        assert!(contract.ft_mint_idempotent("req-2".to_string(), accounts(1).into(), U128(100)));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 200);
+    }
+
+    #[test]
+    fn test_mint_notifies_registry_of_new_holder() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_registry_contract(Some(accounts(4)));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(100));
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(4).as_ref()));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(100));
+        let receipts = get_created_receipts();
+        assert!(!receipts.iter().any(|receipt| &receipt.receiver_id == accounts(4).as_ref()));
+    }
+
+    #[test]
+    fn test_storage_balance_of_registered_account() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        assert!(contract.storage_balance_of(accounts(1)).is_none());
+
+        register_account(&mut context, &mut contract, accounts(1));
+        let storage_balance = contract.storage_balance_of(accounts(1)).unwrap();
+        assert_eq!(storage_balance.total, contract.storage_balance_bounds().min);
+        assert_eq!(storage_balance.available.0, 0);
+    }
+
+    #[test]
+    fn test_register_and_mint_new_account() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        assert!(contract.storage_balance_of(accounts(3)).is_none());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        let before = env::storage_usage();
+        contract.register_and_mint(accounts(3), U128(100));
+        assert!(contract.storage_balance_of(accounts(3)).is_some());
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 100);
+        assert!(env::storage_usage() - before >= contract.token.account_storage_usage);
+    }
+
+    #[test]
+    #[should_panic(expected = "to cover storage")]
+    fn test_register_and_mint_new_account_without_deposit() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(0).build());
+        contract.register_and_mint(accounts(3), U128(100));
+    }
+
+    #[test]
+    fn test_register_and_mint_existing_account() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.register_and_mint(accounts(3), U128(100));
+
+        // Neither a registration nor a new minter entry is needed the second time.
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(0).build());
+        contract.register_and_mint(accounts(3), U128(100));
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 200);
+    }
+
+    const MINT_FEE: Balance = 10_000;
+
+    #[test]
+    fn test_mint_fee_paid_exactly() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_treasury(accounts(3));
+        contract.set_mint_fee(U128(MINT_FEE));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT + MINT_FEE)
+            .build());
+        contract.ft_mint(accounts(2).into(), U128(100));
+        assert_eq!(contract.total_fees_collected().0, MINT_FEE);
+
+        // The second mint rewrites the same records, so it uses no new storage.
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_FEE)
+            .build());
+        contract.ft_mint(accounts(2).into(), U128(100));
+        let receipts = get_created_receipts();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(&receipts[0].receiver_id, accounts(3).as_ref());
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY + 200);
+        assert_eq!(contract.total_fees_collected().0, 2 * MINT_FEE);
+    }
+
+    #[test]
+    fn test_mint_fee_overpaid_is_refunded() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_treasury(accounts(3));
+        contract.set_mint_fee(U128(MINT_FEE));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT + MINT_FEE)
+            .build());
+        contract.ft_mint(accounts(2).into(), U128(100));
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(3).as_ref()));
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(2).as_ref()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Must attach a mint fee of 10000 yoctoNEAR")]
+    fn test_mint_fee_underpaid() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_mint_fee(U128(MINT_FEE));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_FEE - 1)
+            .build());
+        contract.ft_mint(accounts(2).into(), U128(100));
+    }
+
+    pub(crate) fn contract_with_decimals(decimals: u8) -> Contract {
+        Contract::new(
+            accounts(2),
+            TOTAL_SUPPLY.into(),
+            FungibleTokenMetadata {
+                spec: FT_METADATA_SPEC.to_string(),
+                name: "BlaBla Token".to_string(),
+                symbol: "BLABLA".to_string(),
+                icon: None,
+                reference: None,
+                reference_hash: None,
+                decimals,
+            },
+        )
+    }
+
+    #[test]
+    fn test_whole_units_only_mint() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = contract_with_decimals(2);
+        contract.set_whole_units_only(true);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(300));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 300);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only mint whole tokens")]
+    fn test_whole_units_only_rejects_fraction() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = contract_with_decimals(2);
+        contract.set_whole_units_only(true);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(150));
+    }
+
+    #[test]
+    fn test_mint_to_self_only() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_mint_to_self_only(true);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(100));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+    }
+
+    #[test]
+    #[should_panic(expected = "Can only mint to the predecessor account")]
+    fn test_mint_to_self_only_rejects_other_receiver() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_mint_to_self_only(true);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.ft_mint(accounts(3).into(), U128(100));
+    }
 }
@@ -0,0 +1,185 @@
+//! Migration to a successor token: `melt` burns tokens here and has the successor contract
+//! pay them out to the same account through `ft_transfer`.
+use crate::*;
+
+const GAS_FOR_SUCCESSOR_TRANSFER: Gas = 10_000_000_000_000;
+const GAS_FOR_RESOLVE_MELT: Gas = 5_000_000_000_000;
+const ONE_YOCTO: Balance = 1;
+
+#[ext_contract(ext_successor)]
+pub trait SuccessorToken {
+    fn ft_transfer(&mut self, receiver_id: AccountId, amount: U128, memo: Option<String>);
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Burns `amount` of the caller's tokens under the rules of `ft_burn` and credits the same
+    /// amount on the successor contract, which pays it out of the balance this contract holds
+    /// there. If the successor transfer fails, `melt_resolve` mints the tokens back here.
+    #[payable]
+    pub fn melt(&mut self, amount: U128) -> Promise {
+        assert_one_yocto();
+        let successor_id = self.successor_contract.clone().expect("No successor contract is set");
+        let account_id = env::predecessor_account_id();
+        let amount: Balance = amount.into();
+        self.assert_below_large_burn_threshold(amount);
+        self.internal_checked_burn(account_id.clone(), amount, Some("melt"));
+        self.pending_callbacks += 1;
+
+        ext_successor::ft_transfer(
+            account_id.clone(),
+            amount.into(),
+            Some("melt".to_string()),
+            &successor_id,
+            ONE_YOCTO,
+            GAS_FOR_SUCCESSOR_TRANSFER,
+        )
+        .then(ext_self::melt_resolve(
+            account_id,
+            amount.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_MELT,
+        ))
+    }
+
+    /// Mints the melted tokens back if the successor transfer failed, to `fallback_receiver`
+    /// if the account unregistered in the meantime. Returns whether the migration went
+    /// through.
+    #[private]
+    pub fn melt_resolve(&mut self, account_id: AccountId, amount: U128) -> bool {
+        self.pending_callbacks = self.pending_callbacks.saturating_sub(1);
+        match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(_) => true,
+            PromiseResult::Failed => {
+                let refund_id = if self.token.accounts.contains_key(&account_id) {
+                    Some(account_id.clone())
+                } else {
+                    self.fallback_receiver
+                        .clone()
+                        .filter(|fallback_id| self.token.accounts.contains_key(fallback_id))
+                };
+                if let Some(refund_id) = refund_id {
+                    let balance = self.token.accounts.get(&refund_id).unwrap();
+                    self.token.internal_deposit(&refund_id, amount.into());
+                    self.token.total_supply += amount.0;
+                    self.internal_track_holder(balance, balance + amount.0);
+                    self.internal_update_twab(&refund_id);
+                    self.emit_event(Nep141Event::FtMint(vec![FtMint {
+                        owner_id: &refund_id,
+                        amount,
+                        memo: Some("melt refund"),
+                    }]));
+                } else {
+                    log!(
+                        "No registered refund account for @{}, {} melted tokens stay burned",
+                        account_id,
+                        amount.0
+                    );
+                }
+                false
+            }
+        }
+    }
+
+    pub fn set_successor_contract(&mut self, successor_contract: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.successor_contract = successor_contract.map(|account_id| account_id.into());
+    }
+
+    pub fn get_successor_contract(&self) -> Option<AccountId> {
+        self.successor_contract.clone()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_created_receipts, testing_env_with_promise_results};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_melt_burns_and_calls_successor() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_successor_contract(Some(accounts(4)));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.melt(U128(300));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 300);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 300);
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(4).as_ref()));
+    }
+
+    #[test]
+    fn test_melt_failure_mints_back() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_successor_contract(Some(accounts(4)));
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.melt(U128(300));
+
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(0)
+                .predecessor_account_id(accounts(0))
+                .build(),
+            PromiseResult::Failed,
+        );
+        assert!(!contract.melt_resolve(accounts(2).into(), U128(300)));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    #[should_panic(expected = "No successor contract is set")]
+    fn test_melt_without_successor() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        testing_env!(context.attached_deposit(1).build());
+        contract.melt(U128(300));
+    }
+
+    #[test]
+    fn test_melt_failure_refunds_fallback() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_successor_contract(Some(accounts(4)));
+        contract.set_fallback_receiver(Some(accounts(2)));
+
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(0)
+                .predecessor_account_id(accounts(0))
+                .build(),
+            PromiseResult::Failed,
+        );
+        assert!(!contract.melt_resolve(accounts(3).into(), U128(300)));
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY + 300);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 300);
+    }
+
+    #[test]
+    #[should_panic(expected = "Burns above the threshold need a confirmation")]
+    fn test_melt_above_large_burn_threshold() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_successor_contract(Some(accounts(4)));
+        contract.set_large_burn_threshold(Some(U128(100)));
+        testing_env!(context.attached_deposit(1).build());
+        contract.melt(U128(300));
+    }
+}
@@ -0,0 +1,239 @@
+use near_sdk::serde::Deserialize;
+use near_sdk::serde_json::{self, Value};
+
+use crate::*;
+
+/// Upper bounds that keep the memo check within a fixed gas budget.
+pub const MAX_BANNED_MEMO_TERMS: usize = 50;
+pub const MAX_BANNED_MEMO_TERM_LEN: usize = 32;
+pub const MAX_BANNED_MSG_PREFIXES: usize = 50;
+pub const MAX_BANNED_MSG_PREFIX_LEN: usize = 64;
+
+/// How a receiver wants the memos of incoming transfers to be encoded.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum MemoEncoding {
+    /// Any UTF-8 text, the default.
+    Utf8,
+    /// Standard base64 only.
+    Base64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Replaces the list of substrings that transfers reject in memos. Matching ignores ASCII
+    /// case.
+    pub fn set_banned_memo_terms(&mut self, terms: Vec<String>) {
+        self.assert_owner();
+        assert!(terms.len() <= MAX_BANNED_MEMO_TERMS, "Too many banned memo terms");
+        self.banned_memo_terms = terms
+            .into_iter()
+            .map(|term| {
+                assert!(
+                    !term.is_empty() && term.len() <= MAX_BANNED_MEMO_TERM_LEN,
+                    "Banned memo terms must be 1 to {} bytes",
+                    MAX_BANNED_MEMO_TERM_LEN
+                );
+                term.to_ascii_lowercase()
+            })
+            .collect();
+    }
+
+    pub fn get_banned_memo_terms(&self) -> Vec<String> {
+        self.banned_memo_terms.clone()
+    }
+
+    /// Replaces the list of prefixes that `ft_transfer_call` rejects in `msg`. Matching is
+    /// exact, including case.
+    pub fn set_banned_msg_prefixes(&mut self, prefixes: Vec<String>) {
+        self.assert_owner();
+        assert!(prefixes.len() <= MAX_BANNED_MSG_PREFIXES, "Too many banned msg prefixes");
+        for prefix in &prefixes {
+            assert!(
+                !prefix.is_empty() && prefix.len() <= MAX_BANNED_MSG_PREFIX_LEN,
+                "Banned msg prefixes must be 1 to {} bytes",
+                MAX_BANNED_MSG_PREFIX_LEN
+            );
+        }
+        self.banned_msg_prefixes = prefixes;
+    }
+
+    pub fn get_banned_msg_prefixes(&self) -> Vec<String> {
+        self.banned_msg_prefixes.clone()
+    }
+
+    /// Sets the encoding that direct transfers to the caller must use for their memos. The
+    /// caller pays for the storage of a non-default encoding.
+    #[payable]
+    pub fn set_memo_encoding(&mut self, encoding: MemoEncoding) {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        match encoding {
+            MemoEncoding::Utf8 => {
+                self.memo_encodings.remove(&account_id);
+            }
+            MemoEncoding::Base64 => {
+                self.memo_encodings.insert(&account_id, &encoding);
+            }
+        }
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    pub fn get_memo_encoding(&self, account_id: ValidAccountId) -> MemoEncoding {
+        self.memo_encodings.get(account_id.as_ref()).unwrap_or(MemoEncoding::Utf8)
+    }
+
+    /// Makes transfers without a non-empty memo fail, including queued withdrawals and large
+    /// transfers, which carry the memo they were requested with.
+    pub fn set_require_memo(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.require_memo = enabled;
+    }
+
+    pub fn is_memo_required(&self) -> bool {
+        self.require_memo
+    }
+}
+
+impl Contract {
+    pub(crate) fn assert_memo_allowed(&self, receiver_id: &AccountId, memo: &Option<String>) {
+        if self.require_memo {
+            assert!(memo.as_ref().map_or(false, |memo| !memo.is_empty()), "Memo required");
+        }
+        if let (Some(memo), Some(MemoEncoding::Base64)) =
+            (memo, self.memo_encodings.get(receiver_id))
+        {
+            assert!(
+                serde_json::from_value::<Base64VecU8>(Value::String(memo.clone())).is_ok(),
+                "Receiver {} only accepts base64 memos",
+                receiver_id
+            );
+        }
+        if let Some(memo) = memo {
+            if self.banned_memo_terms.is_empty() {
+                return;
+            }
+            let memo = memo.to_ascii_lowercase();
+            for term in &self.banned_memo_terms {
+                assert!(!memo.contains(term.as_str()), "Memo contains a banned term");
+            }
+        }
+    }
+
+    pub(crate) fn assert_msg_allowed(&self, msg: &str) {
+        for prefix in &self.banned_msg_prefixes {
+            assert!(!msg.starts_with(prefix.as_str()), "Message has a banned prefix");
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_banned_memo_terms(vec!["scam".to_string()]);
+        register_account(&mut context, &mut contract, accounts(1));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_clean_memo() {
+        let (_context, mut contract) = setup();
+        contract.ft_transfer(accounts(1), U128(10), Some("invoice 42".to_string()));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo contains a banned term")]
+    fn test_banned_memo() {
+        let (_context, mut contract) = setup();
+        contract.ft_transfer(accounts(1), U128(10), Some("Not a SCAM".to_string()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo required")]
+    fn test_required_memo_missing() {
+        let (mut context, mut contract) = setup();
+        contract.set_require_memo(true);
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(1), U128(10), Some(String::new()));
+    }
+
+    #[test]
+    fn test_required_memo_present() {
+        let (mut context, mut contract) = setup();
+        contract.set_require_memo(true);
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(1), U128(10), Some("invoice 42".to_string()));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Message has a banned prefix")]
+    fn test_banned_msg_prefix() {
+        let (mut context, mut contract) = setup();
+        contract.set_banned_msg_prefixes(vec!["drain:".to_string()]);
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer_call(accounts(1), U128(10), None, "drain:all".to_string());
+    }
+
+    #[test]
+    fn test_allowed_msg() {
+        let (mut context, mut contract) = setup();
+        contract.set_banned_msg_prefixes(vec!["drain:".to_string()]);
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer_call(accounts(1), U128(10), None, "deposit".to_string());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+    }
+
+    #[test]
+    fn test_base64_memo_for_base64_receiver() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_memo_encoding(MemoEncoding::Base64);
+        assert_eq!(contract.get_memo_encoding(accounts(1)), MemoEncoding::Base64);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(10), Some("aW52b2ljZSA0Mg==".to_string()));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "Receiver bob only accepts base64 memos")]
+    fn test_plain_memo_for_base64_receiver() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.set_memo_encoding(MemoEncoding::Base64);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(10), Some("invoice 42".to_string()));
+    }
+}
@@ -0,0 +1,207 @@
+use near_sdk::json_types::Base64VecU8;
+
+use crate::*;
+
+/// Default cap on the length of the metadata `icon` string.
+pub const DEFAULT_MAX_ICON_BYTES: u32 = 65_536;
+
+pub(crate) fn assert_icon_size(icon: &Option<String>, max_icon_bytes: u32) {
+    if let Some(icon) = icon {
+        assert!(
+            icon.len() as u64 <= max_icon_bytes as u64,
+            "Icon exceeds {} bytes",
+            max_icon_bytes
+        );
+    }
+}
+
+/// Accepts `ft-1.x.y` spec versions, the family this contract implements.
+pub(crate) fn is_recognized_spec(spec: &str) -> bool {
+    match spec.strip_prefix("ft-1.") {
+        Some(version) => {
+            let parts: Vec<&str> = version.split('.').collect();
+            parts.len() == 2
+                && parts
+                    .iter()
+                    .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+        }
+        None => false,
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn set_spec(&mut self, spec: String) {
+        self.assert_owner();
+        assert!(is_recognized_spec(&spec), "Unrecognized metadata spec {}", spec);
+        let mut metadata = self.metadata.get().unwrap();
+        metadata.spec = spec;
+        self.metadata.set(&metadata);
+    }
+
+    pub fn set_icon(&mut self, icon: Option<String>) {
+        self.assert_owner();
+        assert_icon_size(&icon, self.max_icon_bytes);
+        let mut metadata = self.metadata.get().unwrap();
+        metadata.icon = icon;
+        self.metadata.set(&metadata);
+    }
+
+    /// Sets the longest `icon` accepted by `set_icon`. Does not touch the current icon.
+    pub fn set_max_icon_bytes(&mut self, max_icon_bytes: u32) {
+        self.assert_owner();
+        self.max_icon_bytes = max_icon_bytes;
+    }
+
+    pub fn get_max_icon_bytes(&self) -> u32 {
+        self.max_icon_bytes
+    }
+
+    /// Sets the off-chain `reference` document and its sha256 `reference_hash`. Both must be
+    /// set or both cleared.
+    pub fn set_reference(
+        &mut self,
+        reference: Option<String>,
+        reference_hash: Option<Base64VecU8>,
+    ) {
+        self.assert_owner();
+        assert!(!self.reference_frozen, "Metadata reference is frozen");
+        let mut metadata = self.metadata.get().unwrap();
+        metadata.reference = reference;
+        metadata.reference_hash = reference_hash;
+        metadata.assert_valid();
+        self.metadata.set(&metadata);
+    }
+
+    /// Permanently locks `reference` and `reference_hash`. Other metadata stays editable.
+    pub fn freeze_reference(&mut self) {
+        self.assert_owner();
+        self.reference_frozen = true;
+    }
+
+    pub fn is_reference_frozen(&self) -> bool {
+        self.reference_frozen
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn metadata_with_icon(icon: String) -> FungibleTokenMetadata {
+        FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "Example".to_string(),
+            symbol: "EXMPL".to_string(),
+            icon: Some(icon),
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        }
+    }
+
+    #[test]
+    fn test_set_spec() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_spec("ft-1.1.0".to_string());
+        assert_eq!(contract.ft_metadata().spec, "ft-1.1.0");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unrecognized metadata spec ft-2.0.0")]
+    fn test_set_spec_rejects_unknown() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_spec("ft-2.0.0".to_string());
+    }
+
+    #[test]
+    fn test_is_recognized_spec() {
+        assert!(is_recognized_spec("ft-1.0.0"));
+        assert!(is_recognized_spec("ft-1.10.2"));
+        assert!(!is_recognized_spec("ft-1.0"));
+        assert!(!is_recognized_spec("ft-1.0.x"));
+        assert!(!is_recognized_spec("nft-1.0.0"));
+    }
+
+    #[test]
+    fn test_set_icon_at_limit() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_max_icon_bytes(16);
+        contract.set_icon(Some("x".repeat(16)));
+        assert_eq!(contract.ft_metadata().icon, Some("x".repeat(16)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Icon exceeds 16 bytes")]
+    fn test_set_icon_over_limit() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_max_icon_bytes(16);
+        contract.set_icon(Some("x".repeat(17)));
+    }
+
+    #[test]
+    fn test_set_reference() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let hash = Base64VecU8(vec![7; 32]);
+        contract.set_reference(Some("ipfs://whitepaper".to_string()), Some(hash.clone()));
+        let metadata = contract.ft_metadata();
+        assert_eq!(metadata.reference, Some("ipfs://whitepaper".to_string()));
+        assert_eq!(metadata.reference_hash.map(|hash| hash.0), Some(hash.0));
+    }
+
+    #[test]
+    fn test_icon_editable_after_reference_freeze() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.freeze_reference();
+        assert!(contract.is_reference_frozen());
+        contract.set_icon(Some("x".repeat(16)));
+        assert_eq!(contract.ft_metadata().icon, Some("x".repeat(16)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Metadata reference is frozen")]
+    fn test_set_reference_after_freeze() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.freeze_reference();
+        contract
+            .set_reference(Some("ipfs://whitepaper".to_string()), Some(Base64VecU8(vec![7; 32])));
+    }
+
+    #[test]
+    fn test_new_with_icon_at_default_limit() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let icon = "x".repeat(DEFAULT_MAX_ICON_BYTES as usize);
+        let contract =
+            Contract::new(accounts(2), TOTAL_SUPPLY.into(), metadata_with_icon(icon.clone()));
+        assert_eq!(contract.ft_metadata().icon, Some(icon));
+    }
+
+    #[test]
+    #[should_panic(expected = "Icon exceeds 65536 bytes")]
+    fn test_new_with_icon_over_default_limit() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let icon = "x".repeat(DEFAULT_MAX_ICON_BYTES as usize + 1);
+        Contract::new(accounts(2), TOTAL_SUPPLY.into(), metadata_with_icon(icon));
+    }
+}
@@ -0,0 +1,133 @@
+//! Upgrade from the original layout, which only stored the balances and the metadata. The new
+//! code is deployed together with a `migrate` call from the contract account. The old balance
+//! map has no index, so `migrate` takes the registered accounts and checks that they hold the
+//! whole total supply. New accounts pay the larger storage deposit of the new layout, while the
+//! migrated ones keep the deposit they paid before.
+use crate::*;
+
+/// The state as stored by the original contract.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub(crate) struct BaselineContract {
+    pub token: FungibleToken,
+    pub metadata: LazyOption<FungibleTokenMetadata>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Rebuilds the state from the original layout with `owner_id` as the owner and every
+    /// other setting at its default. `account_ids` should list every registered account;
+    /// they are added to the account index and must hold the whole total supply. Closing a
+    /// listed account refunds the storage deposit of the original layout. An unlisted account
+    /// would be refunded the new, larger one, so zero balances must be listed too.
+    #[init(ignore_state)]
+    pub fn migrate(owner_id: ValidAccountId, account_ids: Vec<ValidAccountId>) -> Self {
+        assert_eq!(
+            env::predecessor_account_id(),
+            env::current_account_id(),
+            "Can only be called by the contract account"
+        );
+        let baseline: BaselineContract =
+            env::state_read().unwrap_or_else(|| env::panic(b"No state to migrate"));
+        let baseline_deposit =
+            Balance::from(baseline.token.account_storage_usage) * env::storage_byte_cost();
+        let mut this = Self::with_token(owner_id.into(), baseline.token, baseline.metadata);
+        let mut indexed_supply: Balance = 0;
+        for account_id in account_ids.iter() {
+            let account_id: &AccountId = account_id.as_ref();
+            let balance = this.token.internal_unwrap_balance_of(account_id);
+            assert!(
+                !this.registered_accounts.contains(account_id),
+                "The account {} is listed twice",
+                account_id
+            );
+            this.internal_index_account(account_id);
+            this.storage_deposits.insert(account_id, &baseline_deposit);
+            this.internal_track_holder(0, balance);
+            this.internal_update_twab(account_id);
+            indexed_supply += balance;
+        }
+        assert_eq!(
+            indexed_supply, this.token.total_supply,
+            "The listed accounts must hold the whole total supply"
+        );
+        log!("Migrated {} accounts", account_ids.len());
+        this
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, TOTAL_SUPPLY};
+
+    fn write_baseline_state() -> Balance {
+        let mut token = FungibleToken::new(TOKEN_PREFIX.to_vec());
+        token.internal_register_account(accounts(1).as_ref());
+        token.internal_register_account(accounts(3).as_ref());
+        token.internal_deposit(accounts(1).as_ref(), TOTAL_SUPPLY - 100);
+        token.internal_deposit(accounts(3).as_ref(), 100);
+        let metadata = FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "BlaBla Token".to_string(),
+            symbol: "BLABLA".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        };
+        let deposit = token.storage_balance_bounds().min.0;
+        env::state_write(&BaselineContract {
+            token,
+            metadata: LazyOption::new(b"m".to_vec(), Some(&metadata)),
+        });
+        deposit
+    }
+
+    #[test]
+    fn test_migrate_from_baseline() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let baseline_deposit = write_baseline_state();
+
+        let mut contract = Contract::migrate(accounts(2), vec![accounts(1), accounts(3)]);
+        assert_eq!(contract.get_owner(), accounts(2).to_string());
+        assert_eq!(contract.ft_metadata().symbol, "BLABLA");
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, TOTAL_SUPPLY - 100);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 100);
+        assert_eq!(contract.holder_count(), 2);
+        assert!(contract.storage_balance_bounds().min.0 > baseline_deposit);
+        assert_eq!(contract.storage_balance_of(accounts(3)).unwrap().total.0, baseline_deposit);
+
+        register_account(&mut context, &mut contract, accounts(4));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.ft_transfer(accounts(4), U128(100), None);
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 100);
+        assert_eq!(contract.holder_count(), 2);
+        assert_eq!(
+            contract.storage_balance_of(accounts(4)).unwrap().total,
+            contract.storage_balance_bounds().min
+        );
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        assert!(contract.storage_unregister(None));
+        assert!(contract.storage_balance_of(accounts(3)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The listed accounts must hold the whole total supply")]
+    fn test_migrate_with_missing_account() {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        write_baseline_state();
+        Contract::migrate(accounts(2), vec![accounts(1)]);
+    }
+}
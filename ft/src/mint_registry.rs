@@ -0,0 +1,185 @@
+//! Mint destination checks against an address registry. While `mint_registry` is set,
+//! `ft_mint` asks the registry whether the receiver is listed and mints in
+//! `on_mint_destination_checked` only if the answer is `true`. Other mint methods are disabled.
+use crate::*;
+
+const GAS_FOR_REGISTRY_CHECK: Gas = 10_000_000_000_000;
+const GAS_FOR_RESOLVE_REGISTRY_CHECK: Gas = 20_000_000_000_000;
+
+#[ext_contract(ext_mint_registry)]
+pub trait MintRegistry {
+    fn has_account(&self, account_id: AccountId) -> bool;
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the registry that every `ft_mint` receiver must be listed in. `None` makes mints
+    /// synchronous again.
+    pub fn set_mint_registry(&mut self, mint_registry: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.mint_registry = mint_registry.map(|account_id| account_id.into());
+    }
+
+    pub fn get_mint_registry(&self) -> Option<AccountId> {
+        self.mint_registry.clone()
+    }
+
+    /// Mints `amount` to `receiver_id` if the registry listed it, paying the storage from
+    /// `deposit` and refunding the rest to `minter_id`. A rejected mint refunds the whole
+    /// deposit. Returns whether the tokens were minted.
+    #[private]
+    pub fn on_mint_destination_checked(
+        &mut self,
+        minter_id: AccountId,
+        receiver_id: AccountId,
+        amount: U128,
+        deposit: U128,
+    ) -> bool {
+        let deposit: Balance = deposit.into();
+        let listed = match env::promise_result(0) {
+            PromiseResult::NotReady => unreachable!(),
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(false)
+            }
+            PromiseResult::Failed => false,
+        };
+        if !listed {
+            log!("Mint registry does not list @{}, nothing was minted", receiver_id);
+            if deposit > 0 {
+                Promise::new(minter_id).transfer(deposit);
+            }
+            return false;
+        }
+
+        let initial_storage_usage = env::storage_usage();
+        self.internal_mint_by(&minter_id, &receiver_id, amount.into());
+        let required_cost =
+            env::storage_byte_cost() * Balance::from(env::storage_usage() - initial_storage_usage);
+        assert!(
+            required_cost <= deposit,
+            "Must attach {} yoctoNEAR to cover storage",
+            required_cost
+        );
+        let refund = deposit - required_cost;
+        if refund > 1 {
+            Promise::new(minter_id).transfer(refund);
+        }
+        true
+    }
+}
+
+impl Contract {
+    /// Asks the mint registry about `receiver_id` and mints in the callback. `deposit` is the
+    /// part of the attached deposit left for storage.
+    pub(crate) fn internal_request_mint_check(
+        &mut self,
+        registry_id: &AccountId,
+        receiver_id: AccountId,
+        amount: Balance,
+        deposit: Balance,
+    ) {
+        assert!(!self.supply_locked, "Total supply is locked");
+        assert!(!self.minting_paused, "Minting is paused");
+        ext_mint_registry::has_account(
+            receiver_id.clone(),
+            registry_id,
+            NO_DEPOSIT,
+            GAS_FOR_REGISTRY_CHECK,
+        )
+        .then(ext_self::on_mint_destination_checked(
+            env::predecessor_account_id(),
+            receiver_id,
+            amount.into(),
+            deposit.into(),
+            &env::current_account_id(),
+            NO_DEPOSIT,
+            GAS_FOR_RESOLVE_REGISTRY_CHECK,
+        ));
+    }
+
+    pub(crate) fn assert_no_mint_registry(&self) {
+        assert!(self.mint_registry.is_none(), "Mints need a registry check, use ft_mint");
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{
+        accounts, get_created_receipts, testing_env_with_promise_results, VMContextBuilder,
+    };
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        contract.set_mint_registry(Some(accounts(4)));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_mint(accounts(1).into(), U128(100));
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(4).as_ref()));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        (context, contract)
+    }
+
+    fn resolve(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        result: PromiseResult,
+    ) -> bool {
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(0)
+                .predecessor_account_id(accounts(0))
+                .build(),
+            result,
+        );
+        contract.on_mint_destination_checked(
+            accounts(2).into(),
+            accounts(1).into(),
+            U128(100),
+            U128(MINT_DEPOSIT),
+        )
+    }
+
+    #[test]
+    fn test_mint_to_listed_receiver() {
+        let (mut context, mut contract) = setup();
+        assert!(resolve(&mut context, &mut contract, PromiseResult::Successful(b"true".to_vec())));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY + 100);
+    }
+
+    #[test]
+    fn test_mint_to_unlisted_receiver() {
+        let (mut context, mut contract) = setup();
+        assert!(!resolve(
+            &mut context,
+            &mut contract,
+            PromiseResult::Successful(b"false".to_vec())
+        ));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 0);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        let receipts = get_created_receipts();
+        assert!(receipts.iter().any(|receipt| &receipt.receiver_id == accounts(2).as_ref()));
+    }
+
+    #[test]
+    #[should_panic(expected = "Mints need a registry check, use ft_mint")]
+    fn test_mint_call_disabled_with_registry() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        contract.ft_mint_call(accounts(1), U128(10), "".to_string());
+    }
+}
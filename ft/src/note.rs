@@ -0,0 +1,191 @@
+use crate::*;
+
+pub const MAX_NOTE_KEY_LEN: usize = 64;
+pub const MAX_NOTE_VALUE_LEN: usize = 256;
+
+#[near_bindgen]
+impl Contract {
+    /// Transfers `amount` to `receiver_id` like `ft_transfer` and stores `note_value` under
+    /// `note_key` for the receiver, so invoices can be matched to payments. A later note from
+    /// the same sender with the same key replaces the earlier one; other senders cannot
+    /// overwrite it. Requires at least 1 yoctoNEAR; the caller pays for the storage of the note.
+    #[payable]
+    pub fn ft_transfer_with_note(
+        &mut self,
+        receiver_id: ValidAccountId,
+        amount: U128,
+        memo: Option<String>,
+        note_key: String,
+        note_value: String,
+    ) {
+        assert_at_least_one_yocto();
+        assert!(note_key.len() <= MAX_NOTE_KEY_LEN, "Note key is too long");
+        assert!(note_value.len() <= MAX_NOTE_VALUE_LEN, "Note value is too long");
+        let initial_storage_usage = env::storage_usage();
+        let sender_id = env::predecessor_account_id();
+        let note_id = (receiver_id.clone().into(), note_key);
+        if let Some((note_sender_id, _)) = self.notes.get(&note_id) {
+            assert_eq!(note_sender_id, sender_id, "Note {} was left by another sender", note_id.1);
+        }
+
+        let requested: Balance = amount.into();
+        let amount = self.internal_truncate_amount(requested);
+        self.internal_transfer(&sender_id, receiver_id.as_ref(), amount, memo);
+        self.internal_settle_remainder(&sender_id, requested - amount);
+        self.notes.insert(&note_id, &(sender_id, note_value));
+
+        refund_deposit(env::storage_usage().saturating_sub(initial_storage_usage));
+    }
+
+    pub fn get_note(&self, account_id: ValidAccountId, note_key: String) -> Option<String> {
+        self.notes.get(&(account_id.into(), note_key)).map(|(_, note_value)| note_value)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_transfer_with_note() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer_with_note(
+            accounts(1),
+            U128(100),
+            None,
+            "invoice".to_string(),
+            "INV-42".to_string(),
+        );
+
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+        assert_eq!(
+            contract.get_note(accounts(1), "invoice".to_string()),
+            Some("INV-42".to_string())
+        );
+        assert_eq!(contract.get_note(accounts(2), "invoice".to_string()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of at least 1 yoctoNEAR")]
+    fn test_transfer_with_note_without_deposit() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer_with_note(
+            accounts(1),
+            U128(100),
+            None,
+            "invoice".to_string(),
+            "INV-42".to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Note invoice was left by another sender")]
+    fn test_note_overwrite_by_other_sender() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer_with_note(
+            accounts(3),
+            U128(100),
+            None,
+            "gift".to_string(),
+            "hi".to_string(),
+        );
+        contract.ft_transfer_with_note(
+            accounts(1),
+            U128(100),
+            None,
+            "invoice".to_string(),
+            "INV-42".to_string(),
+        );
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.ft_transfer_with_note(
+            accounts(1),
+            U128(10),
+            None,
+            "invoice".to_string(),
+            "PAID".to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo required")]
+    fn test_transfer_with_note_requires_memo() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_require_memo(true);
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer_with_note(
+            accounts(1),
+            U128(100),
+            None,
+            "invoice".to_string(),
+            "INV-42".to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Note value is too long")]
+    fn test_transfer_with_oversized_note() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer_with_note(
+            accounts(1),
+            U128(100),
+            None,
+            "invoice".to_string(),
+            "x".repeat(MAX_NOTE_VALUE_LEN + 1),
+        );
+    }
+}
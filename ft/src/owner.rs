@@ -0,0 +1,361 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    pub(crate) fn assert_owner(&self) {
+        assert_eq!(env::predecessor_account_id(), self.owner_id, "Can only be called by the owner");
+    }
+
+    pub fn get_owner(&self) -> AccountId {
+        self.owner_id.clone()
+    }
+
+    /// Restricts `ft_mint` to crediting the caller, for permissionless faucets.
+    pub fn set_mint_to_self_only(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.mint_to_self_only = enabled;
+    }
+
+    pub fn is_mint_to_self_only(&self) -> bool {
+        self.mint_to_self_only
+    }
+
+    /// Switches NEP-297 event logs for mints, transfers and burns on or off. Turning them off
+    /// saves log gas but breaks indexers that rebuild balances from events; the plain text
+    /// logs of the standard are still written either way.
+    pub fn set_emit_events(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.emit_events = enabled;
+    }
+
+    pub fn get_emit_events(&self) -> bool {
+        self.emit_events
+    }
+
+    pub fn set_escrow_agent(&mut self, escrow_agent: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.escrow_agent = escrow_agent.map(|account_id| account_id.into());
+    }
+
+    pub fn get_escrow_agent(&self) -> Option<AccountId> {
+        self.escrow_agent.clone()
+    }
+
+    /// Limits how many transfers one account can send per block. `None` removes the limit.
+    pub fn set_max_transfers_per_block(&mut self, max_transfers: Option<u32>) {
+        self.assert_owner();
+        self.max_transfers_per_block = max_transfers;
+    }
+
+    pub fn get_max_transfers_per_block(&self) -> Option<u32> {
+        self.max_transfers_per_block
+    }
+
+    /// Caps the open reservations an account can send, and separately those it can receive,
+    /// which bounds the id lists `reservations_of` and closing the account read. Applies to
+    /// `reserve`; `hodl_lock` keeps at most one lock per account.
+    pub fn set_max_locks_per_account(&mut self, max_locks: u32) {
+        self.assert_owner();
+        self.max_locks_per_account = max_locks;
+    }
+
+    pub fn get_max_locks_per_account(&self) -> u32 {
+        self.max_locks_per_account
+    }
+
+    /// Stops accounts from sending and receiving in the same block, against sandwich trades
+    /// during a launch.
+    pub fn set_same_block_guard(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.same_block_guard = enabled;
+    }
+
+    pub fn get_same_block_guard(&self) -> bool {
+        self.same_block_guard
+    }
+
+    /// Stops `ft_mint` and `ft_mint_call` without affecting transfers.
+    pub fn pause_minting(&mut self) {
+        self.assert_owner();
+        self.minting_paused = true;
+    }
+
+    pub fn resume_minting(&mut self) {
+        self.assert_owner();
+        self.minting_paused = false;
+    }
+
+    pub fn is_minting_paused(&self) -> bool {
+        self.minting_paused
+    }
+
+    /// Stops every mint path for good, including balance imports and supply-increasing splits.
+    /// There is no way back.
+    pub fn lock_supply(&mut self) {
+        self.assert_owner();
+        self.supply_locked = true;
+        log!("Total supply locked at {}", self.token.total_supply);
+    }
+
+    pub fn is_supply_locked(&self) -> bool {
+        self.supply_locked
+    }
+
+    /// Sets the smallest nonzero balance a transfer may leave on the sender. `None` disables it.
+    pub fn set_min_account_balance(&mut self, min_balance: Option<U128>) {
+        self.assert_owner();
+        self.min_account_balance = min_balance.map(|min_balance| min_balance.into());
+    }
+
+    pub fn get_min_account_balance(&self) -> Option<U128> {
+        self.min_account_balance.map(|min_balance| min_balance.into())
+    }
+
+    /// Sets the amount above which transfers need owner approval. `None` disables approvals.
+    pub fn set_large_transfer_threshold(&mut self, threshold: Option<U128>) {
+        self.assert_owner();
+        self.large_transfer_threshold = threshold.map(|threshold| threshold.into());
+    }
+
+    pub fn get_large_transfer_threshold(&self) -> Option<U128> {
+        self.large_transfer_threshold.map(|threshold| threshold.into())
+    }
+
+    /// Sets the amount above which burns need a confirmation. `None` disables confirmations.
+    pub fn set_large_burn_threshold(&mut self, threshold: Option<U128>) {
+        self.assert_owner();
+        self.large_burn_threshold = threshold.map(|threshold| threshold.into());
+    }
+
+    pub fn get_large_burn_threshold(&self) -> Option<U128> {
+        self.large_burn_threshold.map(|threshold| threshold.into())
+    }
+
+    /// Sets the total supply that burns cannot go below. `None` removes the floor.
+    pub fn set_min_total_supply(&mut self, min_total_supply: Option<U128>) {
+        self.assert_owner();
+        self.min_total_supply = min_total_supply.map(|min_total_supply| min_total_supply.into());
+    }
+
+    pub fn get_min_total_supply(&self) -> Option<U128> {
+        self.min_total_supply.map(|min_total_supply| min_total_supply.into())
+    }
+
+    /// Sets the holder registry that mints notify about new holders. `None` stops notifications.
+    pub fn set_registry_contract(&mut self, registry_contract: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.registry_contract = registry_contract.map(|account_id| account_id.into());
+    }
+
+    pub fn get_registry_contract(&self) -> Option<AccountId> {
+        self.registry_contract.clone()
+    }
+
+    /// Sets the contract that burns notify through `on_burn`. `None` stops notifications.
+    pub fn set_burn_listener(&mut self, burn_listener: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.burn_listener = burn_listener.map(|account_id| account_id.into());
+    }
+
+    pub fn get_burn_listener(&self) -> Option<AccountId> {
+        self.burn_listener.clone()
+    }
+
+    /// Sets the account that receives mint fees.
+    pub fn set_treasury(&mut self, treasury_id: ValidAccountId) {
+        self.assert_owner();
+        self.treasury_id = treasury_id.into();
+    }
+
+    pub fn get_treasury(&self) -> AccountId {
+        self.treasury_id.clone()
+    }
+
+    /// Sets the NEAR fee every mint must attach on top of its storage cost.
+    pub fn set_mint_fee(&mut self, mint_fee_yocto: U128) {
+        self.assert_owner();
+        self.mint_fee_yocto = mint_fee_yocto.into();
+    }
+
+    pub fn get_mint_fee(&self) -> U128 {
+        self.mint_fee_yocto.into()
+    }
+
+    /// Rejects mints of amounts that are not a multiple of `10^decimals`.
+    pub fn set_whole_units_only(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.whole_units_only = enabled;
+    }
+
+    pub fn is_whole_units_only(&self) -> bool {
+        self.whole_units_only
+    }
+
+    /// Logs batch methods such as `sweep_dust` as one combined NEP-297 event instead of one
+    /// event per balance change.
+    pub fn set_aggregate_batch_events(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.aggregate_batch_events = enabled;
+    }
+
+    pub fn get_aggregate_batch_events(&self) -> bool {
+        self.aggregate_batch_events
+    }
+
+    /// Rejects transfers where the sender is also the receiver. Enabled by default.
+    pub fn set_reject_self_transfers(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.reject_self_transfers = enabled;
+    }
+
+    pub fn get_reject_self_transfers(&self) -> bool {
+        self.reject_self_transfers
+    }
+
+    /// Sets the account credited with `ft_transfer_call` refunds whose sender is no longer
+    /// registered. `None` burns such refunds, as the standard does.
+    pub fn set_fallback_receiver(&mut self, fallback_receiver: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.fallback_receiver = fallback_receiver.map(|account_id| account_id.into());
+    }
+
+    pub fn get_fallback_receiver(&self) -> Option<AccountId> {
+        self.fallback_receiver.clone()
+    }
+
+    /// Sets how long an account must have been registered before it can send tokens. The
+    /// owner, the treasury and system accounts are exempt. `None` disables the check.
+    pub fn set_min_account_age_seconds(&mut self, min_age_seconds: Option<u64>) {
+        self.assert_owner();
+        self.min_account_age_seconds = min_age_seconds;
+    }
+
+    pub fn get_min_account_age_seconds(&self) -> Option<u64> {
+        self.min_account_age_seconds
+    }
+
+    /// Lets transfers to unregistered receivers register them, with the storage paid from the
+    /// deposit the sender attaches beyond the 1 yoctoNEAR.
+    pub fn set_auto_register_on_receive(&mut self, enabled: bool) {
+        self.assert_owner();
+        self.auto_register_on_receive = enabled;
+    }
+
+    pub fn get_auto_register_on_receive(&self) -> bool {
+        self.auto_register_on_receive
+    }
+
+    /// Lets `storage_deposit` register new accounts that attach up to `tolerance` yoctoNEAR
+    /// less than the minimum storage balance, for when byte costs change. The contract pays
+    /// the difference, and closing such an account only refunds what it attached.
+    pub fn set_storage_deposit_tolerance(&mut self, tolerance: U128) {
+        self.assert_owner();
+        self.storage_deposit_tolerance = tolerance.into();
+    }
+
+    pub fn get_storage_deposit_tolerance(&self) -> U128 {
+        self.storage_deposit_tolerance.into()
+    }
+
+    pub fn get_config(&self) -> Config {
+        Config {
+            owner_id: self.owner_id.clone(),
+            recovery_address: self.recovery_address.clone(),
+            treasury_id: self.treasury_id.clone(),
+            mint_to_self_only: self.mint_to_self_only,
+            minting_paused: self.minting_paused,
+            supply_locked: self.supply_locked,
+            whole_units_only: self.whole_units_only,
+            mint_fee_yocto: self.mint_fee_yocto.into(),
+            emit_events: self.emit_events,
+            aggregate_batch_events: self.aggregate_batch_events,
+            reject_self_transfers: self.reject_self_transfers,
+            escrow_agent: self.escrow_agent.clone(),
+            max_transfers_per_block: self.max_transfers_per_block,
+            max_locks_per_account: self.max_locks_per_account,
+            same_block_guard: self.same_block_guard,
+            min_account_balance: self.min_account_balance.map(|min_balance| min_balance.into()),
+            min_account_age_seconds: self.min_account_age_seconds,
+            large_transfer_threshold: self
+                .large_transfer_threshold
+                .map(|threshold| threshold.into()),
+            large_burn_threshold: self.large_burn_threshold.map(|threshold| threshold.into()),
+            min_total_supply: self.min_total_supply.map(|min_total_supply| min_total_supply.into()),
+            withdrawal_threshold: self.get_withdrawal_threshold(),
+            withdrawal_delay: self.get_withdrawal_delay(),
+            max_icon_bytes: self.max_icon_bytes,
+            reference_frozen: self.reference_frozen,
+            burn_whitelist_enabled: self.burn_allowed.is_some(),
+            banned_memo_terms: self.banned_memo_terms.clone(),
+            banned_msg_prefixes: self.banned_msg_prefixes.clone(),
+            blackout_windows: self.blackout_windows.clone(),
+            require_memo: self.require_memo,
+            registry_contract: self.registry_contract.clone(),
+            burn_listener: self.burn_listener.clone(),
+            successor_contract: self.successor_contract.clone(),
+            fallback_receiver: self.fallback_receiver.clone(),
+            sponsorship_budget: self.sponsorship_budget.map(|budget| budget.into()),
+            storage_deposit_tolerance: self.storage_deposit_tolerance.into(),
+            auto_register_on_receive: self.auto_register_on_receive,
+            transfer_precision: self.transfer_precision,
+            remainder_policy: self.remainder_policy,
+            compliance_contract: self.compliance_contract.clone(),
+            mint_registry: self.mint_registry.clone(),
+            legacy_contract: self.legacy_contract.clone(),
+            legacy_relayer: self.legacy_relayer.clone(),
+            system_accounts: self.system_accounts.to_vec(),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_get_config() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let config = contract.get_config();
+        assert_eq!(&config.treasury_id, accounts(2).as_ref());
+        assert!(!config.mint_to_self_only);
+        assert!(config.emit_events);
+        assert!(!config.burn_whitelist_enabled);
+
+        contract.set_treasury(accounts(3));
+        contract.set_mint_to_self_only(true);
+        contract.pause_minting();
+        contract.set_mint_fee(U128(10));
+        contract.set_emit_events(false);
+        contract.set_escrow_agent(Some(accounts(4)));
+        contract.set_max_transfers_per_block(Some(3));
+        contract.set_min_account_balance(Some(U128(5)));
+        contract.set_large_transfer_threshold(Some(U128(1000)));
+        contract.set_large_burn_threshold(Some(U128(500)));
+        contract.set_max_icon_bytes(1024);
+        contract.enable_burn_whitelist();
+        contract.set_registry_contract(Some(accounts(1)));
+
+        let config = contract.get_config();
+        assert_eq!(&config.owner_id, accounts(2).as_ref());
+        assert_eq!(&config.treasury_id, accounts(3).as_ref());
+        assert!(config.mint_to_self_only);
+        assert!(config.minting_paused);
+        assert_eq!(config.mint_fee_yocto.0, 10);
+        assert!(!config.emit_events);
+        assert_eq!(config.escrow_agent, Some(accounts(4).into()));
+        assert_eq!(config.max_transfers_per_block, Some(3));
+        assert_eq!(config.min_account_balance.map(|balance| balance.0), Some(5));
+        assert_eq!(config.large_transfer_threshold.map(|threshold| threshold.0), Some(1000));
+        assert_eq!(config.large_burn_threshold.map(|threshold| threshold.0), Some(500));
+        assert_eq!(config.max_icon_bytes, 1024);
+        assert!(config.burn_whitelist_enabled);
+        assert_eq!(config.registry_contract, Some(accounts(1).into()));
+    }
+}
@@ -0,0 +1,152 @@
+//! Cross-contract operations waiting for their resolve callback. `ft_transfer_call` records an
+//! entry for the sender and `ft_mint_call` one for the receiver, since their tokens may still
+//! be refunded or burned; the resolve callbacks clear the entries again.
+//!
+//! An account has at most `MAX_PENDING_OPERATIONS` entries at a time. Their storage is part of
+//! the registration deposit, so `ft_transfer_call`, which only attaches 1 yoctoNEAR, never adds
+//! unpaid storage.
+use crate::*;
+
+/// Entries an account can have pending at once.
+pub const MAX_PENDING_OPERATIONS: usize = 4;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone, Copy, PartialEq, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub enum PendingKind {
+    TransferCall,
+    MintCall,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingOperation {
+    pub kind: PendingKind,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    /// Block timestamp in nanoseconds.
+    pub started_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    pub fn pending_operations(&self, account_id: ValidAccountId) -> Vec<PendingOperation> {
+        self.pending_operations.get(account_id.as_ref()).unwrap_or_default()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_add_pending(
+        &mut self,
+        account_id: &AccountId,
+        kind: PendingKind,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) {
+        let mut operations = self.pending_operations.get(account_id).unwrap_or_default();
+        assert!(
+            operations.len() < MAX_PENDING_OPERATIONS,
+            "Too many pending cross-contract calls for {}",
+            account_id
+        );
+        operations.push(PendingOperation {
+            kind,
+            receiver_id: receiver_id.clone(),
+            amount: amount.into(),
+            started_at: env::block_timestamp().into(),
+        });
+        self.pending_operations.insert(account_id, &operations);
+    }
+
+    /// Clears the oldest entry of `account_id` matching the resolved operation, or of the same
+    /// kind and receiver if a supply split rescaled its amount in the meantime.
+    pub(crate) fn internal_remove_pending(
+        &mut self,
+        account_id: &AccountId,
+        kind: PendingKind,
+        receiver_id: &AccountId,
+        amount: Balance,
+    ) {
+        let mut operations = match self.pending_operations.get(account_id) {
+            Some(operations) => operations,
+            None => return,
+        };
+        let same_operation = |operation: &PendingOperation| {
+            operation.kind == kind && &operation.receiver_id == receiver_id
+        };
+        if let Some(index) = operations
+            .iter()
+            .position(|operation| same_operation(operation) && operation.amount.0 == amount)
+            .or_else(|| operations.iter().position(same_operation))
+        {
+            operations.remove(index);
+        }
+        if operations.is_empty() {
+            self.pending_operations.remove(account_id);
+        } else {
+            self.pending_operations.insert(account_id, &operations);
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, testing_env_with_promise_results};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_transfer_call_pending_until_resolved() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(7)
+            .build());
+        contract.ft_transfer_call(accounts(1), U128(100), None, "".to_string());
+        let pending = contract.pending_operations(accounts(2));
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].kind, PendingKind::TransferCall);
+        assert_eq!(pending[0].receiver_id, accounts(1).to_string());
+        assert_eq!(pending[0].amount.0, 100);
+        assert_eq!(pending[0].started_at.0, 7);
+
+        testing_env_with_promise_results(
+            context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(0)
+                .predecessor_account_id(accounts(0))
+                .build(),
+            PromiseResult::Successful(b"\"0\"".to_vec()),
+        );
+        contract.ft_resolve_transfer(accounts(2), accounts(1), U128(100));
+        assert!(contract.pending_operations(accounts(2)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many pending cross-contract calls")]
+    fn test_pending_operations_are_capped() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        for _ in 0..MAX_PENDING_OPERATIONS {
+            contract.ft_transfer_call(accounts(1), U128(100), None, "".to_string());
+        }
+        assert_eq!(contract.pending_operations(accounts(2)).len(), MAX_PENDING_OPERATIONS);
+        contract.ft_transfer_call(accounts(1), U128(100), None, "".to_string());
+    }
+}
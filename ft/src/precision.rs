@@ -0,0 +1,156 @@
+use near_sdk::serde::Deserialize;
+
+use crate::*;
+
+/// What happens to the part of a transfer amount cut off by `transfer_precision`.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, PartialEq, Debug,
+)]
+#[serde(crate = "near_sdk::serde")]
+pub enum RemainderPolicy {
+    /// The remainder stays with the sender.
+    Refund,
+    /// The remainder is burned from the sender.
+    Burn,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Truncates the amounts of `ft_transfer`, `ft_transfer_call` and `ft_transfer_all` to
+    /// `precision` decimals, handling the cut-off part per `policy`. `None` switches
+    /// truncation off.
+    pub fn set_transfer_precision(&mut self, precision: Option<u8>, policy: RemainderPolicy) {
+        self.assert_owner();
+        if let Some(precision) = precision {
+            assert!(
+                precision <= self.metadata.get().unwrap().decimals,
+                "Precision cannot exceed the token decimals"
+            );
+        }
+        self.transfer_precision = precision;
+        self.remainder_policy = policy;
+    }
+
+    pub fn get_transfer_precision(&self) -> Option<u8> {
+        self.transfer_precision
+    }
+
+    pub fn get_remainder_policy(&self) -> RemainderPolicy {
+        self.remainder_policy
+    }
+}
+
+impl Contract {
+    /// Returns `amount` truncated to `transfer_precision` decimals.
+    pub(crate) fn internal_truncate_amount(&self, amount: Balance) -> Balance {
+        self.internal_try_truncate_amount(amount)
+            .unwrap_or_else(|error| env::panic(error.as_bytes()))
+    }
+
+    /// Same as `internal_truncate_amount`, returning the error instead of panicking.
+    pub(crate) fn internal_try_truncate_amount(&self, amount: Balance) -> Result<Balance, String> {
+        let precision = match self.transfer_precision {
+            Some(precision) => precision,
+            None => return Ok(amount),
+        };
+        let decimals = self.metadata.get().unwrap().decimals;
+        let truncated = 10u128
+            .checked_pow(u32::from(decimals - precision))
+            .map_or(0, |unit| amount - amount % unit);
+        if truncated == 0 {
+            return Err("The amount is below the transfer precision".to_string());
+        }
+        Ok(truncated)
+    }
+
+    /// Returns the part of `remainder` that the remainder policy burns.
+    pub(crate) fn internal_burned_remainder(&self, remainder: Balance) -> Balance {
+        match self.remainder_policy {
+            RemainderPolicy::Burn => remainder,
+            RemainderPolicy::Refund => 0,
+        }
+    }
+
+    /// Checks, before any tokens move, that `sender_id` can both send `amount` and burn the
+    /// part of `remainder` the remainder policy burns.
+    pub(crate) fn check_remainder(
+        &self,
+        sender_id: &AccountId,
+        amount: Balance,
+        remainder: Balance,
+    ) -> Result<(), String> {
+        let burned = self.internal_burned_remainder(remainder);
+        if burned == 0 {
+            return Ok(());
+        }
+        self.check_burn(sender_id, burned)?;
+        self.check_spendable(sender_id, amount + burned)
+    }
+
+    /// Applies the remainder policy to the `remainder` cut off a transfer of `sender_id`. Runs
+    /// once the transfer went through, with the guards of `ft_burn`.
+    pub(crate) fn internal_settle_remainder(&mut self, sender_id: &AccountId, remainder: Balance) {
+        let burned = self.internal_burned_remainder(remainder);
+        if burned > 0 {
+            self.internal_checked_burn(sender_id.clone(), burned, Some("transfer precision"));
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{contract_with_decimals, get_context, register_account, TOTAL_SUPPLY};
+
+    fn setup(policy: RemainderPolicy) -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = contract_with_decimals(4);
+        contract.set_transfer_precision(Some(2), policy);
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_truncated_remainder_refunded() {
+        let (_context, mut contract) = setup(RemainderPolicy::Refund);
+        contract.ft_transfer(accounts(1), U128(12_345), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 12_300);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 12_300);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    fn test_truncated_remainder_burned() {
+        let (_context, mut contract) = setup(RemainderPolicy::Burn);
+        contract.ft_transfer(accounts(1), U128(12_345), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 12_300);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 12_345);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY - 45);
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount is below the transfer precision")]
+    fn test_amount_below_precision() {
+        let (_context, mut contract) = setup(RemainderPolicy::Refund);
+        contract.ft_transfer(accounts(1), U128(99), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "is not allowed to burn")]
+    fn test_remainder_burn_follows_whitelist() {
+        let (_context, mut contract) = setup(RemainderPolicy::Burn);
+        contract.enable_burn_whitelist();
+        contract.ft_transfer(accounts(1), U128(12_345), None);
+    }
+}
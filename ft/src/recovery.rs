@@ -0,0 +1,119 @@
+//! Ownership recovery for lost owner keys. The owner names a `recovery_address`, usually a
+//! multisig. That account can trigger a recovery and, once `RECOVERY_DELAY` has passed without
+//! the owner cancelling it, take over as owner.
+use crate::*;
+
+/// Time between triggering and completing a recovery, in nanoseconds.
+pub const RECOVERY_DELAY: u64 = 30 * 24 * 60 * 60 * 1_000_000_000;
+
+#[near_bindgen]
+impl Contract {
+    /// Sets the account that can recover ownership. Changing it cancels a pending recovery.
+    pub fn set_recovery_address(&mut self, recovery_address: Option<ValidAccountId>) {
+        self.assert_owner();
+        self.recovery_address = recovery_address.map(|account_id| account_id.into());
+        self.recovery_started_at = None;
+    }
+
+    pub fn get_recovery_address(&self) -> Option<AccountId> {
+        self.recovery_address.clone()
+    }
+
+    /// Starts the recovery delay. Only callable by the recovery address.
+    pub fn trigger_emergency_recovery(&mut self) {
+        self.assert_recovery_address();
+        assert!(self.recovery_started_at.is_none(), "Recovery is already in progress");
+        self.recovery_started_at = Some(env::block_timestamp());
+        log!("Emergency recovery triggered by @{}", env::predecessor_account_id());
+    }
+
+    /// Makes the recovery address the owner once the delay has passed.
+    pub fn complete_emergency_recovery(&mut self) {
+        self.assert_recovery_address();
+        let started_at = self.recovery_started_at.expect("No recovery in progress");
+        assert!(
+            env::block_timestamp() >= started_at + RECOVERY_DELAY,
+            "Recovery delay has not passed"
+        );
+        self.owner_id = self.recovery_address.take().unwrap();
+        self.recovery_started_at = None;
+        log!("Ownership recovered by @{}", self.owner_id);
+    }
+
+    /// Stops a pending recovery, showing the owner keys are still in use.
+    pub fn cancel_emergency_recovery(&mut self) {
+        self.assert_owner();
+        assert!(self.recovery_started_at.take().is_some(), "No recovery in progress");
+        log!("Emergency recovery cancelled");
+    }
+
+    /// Returns the block timestamp from which a pending recovery can be completed.
+    pub fn get_recovery_unlocks_at(&self) -> Option<U64> {
+        self.recovery_started_at.map(|started_at| (started_at + RECOVERY_DELAY).into())
+    }
+}
+
+impl Contract {
+    fn assert_recovery_address(&self) {
+        assert_eq!(
+            Some(env::predecessor_account_id()),
+            self.recovery_address,
+            "Can only be called by the recovery address"
+        );
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_recovery_address(Some(accounts(4)));
+
+        testing_env!(context.predecessor_account_id(accounts(4)).block_timestamp(100).build());
+        contract.trigger_emergency_recovery();
+        assert_eq!(contract.get_recovery_unlocks_at(), Some(U64(100 + RECOVERY_DELAY)));
+        (context, contract)
+    }
+
+    #[test]
+    #[should_panic(expected = "Recovery delay has not passed")]
+    fn test_recovery_before_delay() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(100 + RECOVERY_DELAY - 1).build());
+        contract.complete_emergency_recovery();
+    }
+
+    #[test]
+    fn test_recovery_after_delay() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.block_timestamp(100 + RECOVERY_DELAY).build());
+        contract.complete_emergency_recovery();
+        assert_eq!(contract.get_owner(), accounts(4).to_string());
+        assert!(contract.get_recovery_address().is_none());
+        assert!(contract.get_recovery_unlocks_at().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "No recovery in progress")]
+    fn test_cancelled_recovery() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.cancel_emergency_recovery();
+        assert!(contract.get_recovery_unlocks_at().is_none());
+
+        testing_env!(context
+            .predecessor_account_id(accounts(4))
+            .block_timestamp(100 + RECOVERY_DELAY)
+            .build());
+        contract.complete_emergency_recovery();
+    }
+}
@@ -0,0 +1,424 @@
+use crate::*;
+
+/// Default for `max_locks_per_account`.
+pub const DEFAULT_MAX_LOCKS_PER_ACCOUNT: u32 = 20;
+
+/// Tokens set aside by `sender_id` that `receiver_id` can claim until `expires_at`.
+/// The reserved tokens stay in the sender's balance but cannot be spent elsewhere.
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Reservation {
+    pub sender_id: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    /// Block timestamp in nanoseconds.
+    pub expires_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Reserves `amount` of the caller's tokens for `receiver_id` and returns the reservation id.
+    /// The caller pays for the storage of the reservation. Disabled while a compliance contract
+    /// is set.
+    #[payable]
+    pub fn reserve(&mut self, receiver_id: ValidAccountId, amount: U128, expires_at: U64) -> U64 {
+        let initial_storage_usage = env::storage_usage();
+        let sender_id = env::predecessor_account_id();
+        let amount: Balance = amount.into();
+        assert!(amount > 0, "The amount should be a positive number");
+        assert_ne!(&sender_id, receiver_id.as_ref(), "Sender and receiver should be different");
+        assert!(expires_at.0 > env::block_timestamp(), "Expiry must be in the future");
+        self.assert_no_compliance_contract();
+        self.assert_not_frozen(&sender_id);
+        self.assert_transfer_allowed(&sender_id, receiver_id.as_ref());
+        self.assert_spendable(&sender_id, amount);
+        // Claims move the tokens without the transfer guards, so the thresholds apply here.
+        self.assert_below_large_transfer_threshold(&sender_id, amount);
+        self.assert_below_withdrawal_threshold(&sender_id, amount);
+
+        let id = self.next_reservation_id;
+        self.next_reservation_id += 1;
+        self.internal_index_reservation(&sender_id, receiver_id.as_ref(), id);
+        self.reservations.insert(
+            &id,
+            &Reservation {
+                sender_id: sender_id.clone(),
+                receiver_id: receiver_id.into(),
+                amount: amount.into(),
+                expires_at,
+            },
+        );
+        let reserved = self.reserved.get(&sender_id).unwrap_or(0);
+        self.reserved.insert(&sender_id, &(reserved + amount));
+
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        id.into()
+    }
+
+    /// Transfers reserved tokens to the receiver before expiry. `amount` defaults to the whole
+    /// reservation; a partial claim leaves the remainder reserved. Disabled while a compliance
+    /// contract is set, since the claim moves the tokens without a check.
+    pub fn claim_reservation(&mut self, id: U64, amount: Option<U128>) -> U128 {
+        let mut reservation = self.reservations.get(&id.0).expect("Reservation not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            reservation.receiver_id,
+            "Only the receiver can claim the reservation"
+        );
+        assert!(env::block_timestamp() < reservation.expires_at.0, "Reservation has expired");
+        let amount = amount.map(|amount| amount.0).unwrap_or(reservation.amount.0);
+        assert!(amount > 0 && amount <= reservation.amount.0, "Invalid claim amount");
+        self.assert_no_compliance_contract();
+        self.assert_not_frozen(&reservation.sender_id);
+        self.assert_not_frozen(&reservation.receiver_id);
+
+        reservation.amount = (reservation.amount.0 - amount).into();
+        if reservation.amount.0 == 0 {
+            self.internal_remove_reservation(id.0, &reservation);
+        } else {
+            self.reservations.insert(&id.0, &reservation);
+        }
+        self.internal_release_reserved(&reservation.sender_id, amount);
+        self.internal_transfer_unguarded(
+            &reservation.sender_id,
+            &reservation.receiver_id,
+            amount,
+            Some(format!("Reservation {}", id.0)),
+        );
+        amount.into()
+    }
+
+    /// Releases a reservation back to its sender before expiry. Only callable by the receiver.
+    pub fn reject_reservation(&mut self, id: U64) -> U128 {
+        let reservation = self.reservations.get(&id.0).expect("Reservation not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            reservation.receiver_id,
+            "Only the receiver can reject the reservation"
+        );
+        self.internal_remove_reservation(id.0, &reservation);
+        self.internal_release_reserved(&reservation.sender_id, reservation.amount.0);
+        reservation.amount
+    }
+
+    /// Releases an expired reservation back to its sender.
+    pub fn cancel_reservation(&mut self, id: U64) -> U128 {
+        let reservation = self.reservations.get(&id.0).expect("Reservation not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            reservation.sender_id,
+            "Only the sender can cancel the reservation"
+        );
+        assert!(
+            env::block_timestamp() >= reservation.expires_at.0,
+            "Reservation has not expired yet"
+        );
+        self.internal_remove_reservation(id.0, &reservation);
+        self.internal_release_reserved(&reservation.sender_id, reservation.amount.0);
+        reservation.amount
+    }
+
+    pub fn get_reservation(&self, id: U64) -> Option<Reservation> {
+        self.reservations.get(&id.0)
+    }
+
+    pub fn reserved_balance_of(&self, account_id: ValidAccountId) -> U128 {
+        self.reserved.get(account_id.as_ref()).unwrap_or(0).into()
+    }
+
+    /// Returns the open reservations the account sends or receives, with their ids. Expired
+    /// reservations are listed until the sender cancels them.
+    pub fn reservations_of(&self, account_id: ValidAccountId) -> Vec<(U64, Reservation)> {
+        let sent = self.reservation_ids.get(account_id.as_ref()).unwrap_or_default();
+        let received = self.received_reservation_ids.get(account_id.as_ref()).unwrap_or_default();
+        sent.into_iter()
+            .chain(received)
+            .map(|id| (id.into(), self.reservations.get(&id).unwrap()))
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Rejects the reservation if `sender_id` already sends or `receiver_id` already receives
+    /// `max_locks_per_account` open ones. A receiver can free its slots with
+    /// `reject_reservation`.
+    fn internal_index_reservation(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        id: u64,
+    ) {
+        let mut ids = self.reservation_ids.get(sender_id).unwrap_or_default();
+        assert!(
+            ids.len() < self.max_locks_per_account as usize,
+            "Too many open reservations for {}",
+            sender_id
+        );
+        ids.push(id);
+        self.reservation_ids.insert(sender_id, &ids);
+        let mut ids = self.received_reservation_ids.get(receiver_id).unwrap_or_default();
+        assert!(
+            ids.len() < self.max_locks_per_account as usize,
+            "Too many open reservations for {}",
+            receiver_id
+        );
+        ids.push(id);
+        self.received_reservation_ids.insert(receiver_id, &ids);
+    }
+
+    fn internal_remove_reservation(&mut self, id: u64, reservation: &Reservation) {
+        self.reservations.remove(&id);
+        unindex_reservation(&mut self.reservation_ids, &reservation.sender_id, id);
+        unindex_reservation(&mut self.received_reservation_ids, &reservation.receiver_id, id);
+    }
+
+    /// Drops everything held against the balance of a closed account, so nothing is left
+    /// reserved if it registers again. Its sent reservations and HODL lock go with the
+    /// balance, and the reservations it would have received go back to their senders.
+    pub(crate) fn internal_clear_holds(&mut self, account_id: &AccountId) {
+        for id in self.reservation_ids.remove(account_id).unwrap_or_default() {
+            let reservation = self.reservations.remove(&id).unwrap();
+            unindex_reservation(&mut self.received_reservation_ids, &reservation.receiver_id, id);
+        }
+        for id in self.received_reservation_ids.remove(account_id).unwrap_or_default() {
+            let reservation = self.reservations.remove(&id).unwrap();
+            unindex_reservation(&mut self.reservation_ids, &reservation.sender_id, id);
+            self.internal_release_reserved(&reservation.sender_id, reservation.amount.0);
+        }
+        self.hodl_locks.remove(account_id);
+        self.reserved.remove(account_id);
+    }
+
+    pub(crate) fn internal_release_reserved(&mut self, account_id: &AccountId, amount: Balance) {
+        let reserved = self.reserved.get(account_id).unwrap_or(0) - amount;
+        if reserved == 0 {
+            self.reserved.remove(account_id);
+        } else {
+            self.reserved.insert(account_id, &reserved);
+        }
+    }
+}
+
+fn unindex_reservation(
+    index: &mut LookupMap<AccountId, Vec<u64>>,
+    account_id: &AccountId,
+    id: u64,
+) {
+    let mut ids = index.get(account_id).unwrap_or_default();
+    ids.retain(|reservation_id| *reservation_id != id);
+    if ids.is_empty() {
+        index.remove(account_id);
+    } else {
+        index.insert(account_id, &ids);
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    const EXPIRY: u64 = 1_000;
+
+    fn setup() -> (VMContextBuilder, Contract, U64) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .build());
+        let id = contract.reserve(accounts(1), U128(600), EXPIRY.into());
+        (context, contract, id)
+    }
+
+    #[test]
+    fn test_claim_before_expiry() {
+        let (mut context, mut contract, id) = setup();
+        assert_eq!(contract.reserved_balance_of(accounts(2)).0, 600);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(1))
+            .block_timestamp(EXPIRY - 1)
+            .build());
+        assert_eq!(contract.claim_reservation(id, Some(U128(200))).0, 200);
+        assert_eq!(contract.get_reservation(id).unwrap().amount.0, 400);
+        assert_eq!(contract.claim_reservation(id, None).0, 400);
+
+        assert!(contract.get_reservation(id).is_none());
+        assert_eq!(contract.reserved_balance_of(accounts(2)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 600);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 600);
+    }
+
+    #[test]
+    fn test_cancel_after_expiry() {
+        let (mut context, mut contract, id) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(EXPIRY)
+            .build());
+        assert_eq!(contract.cancel_reservation(id).0, 600);
+        assert_eq!(contract.reserved_balance_of(accounts(2)).0, 0);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY);
+    }
+
+    #[test]
+    fn test_reservations_of_both_parties() {
+        let (mut context, mut contract, first_id) = setup();
+        let second_id = contract.reserve(accounts(3), U128(50), EXPIRY.into());
+
+        let sent = contract.reservations_of(accounts(2));
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0].0, first_id);
+        assert_eq!(sent[1].0, second_id);
+        assert_eq!(sent[1].1.amount.0, 50);
+        let received = contract.reservations_of(accounts(1));
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].1.sender_id, accounts(2).to_string());
+        assert_eq!(received[0].1.expires_at.0, EXPIRY);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.claim_reservation(first_id, None);
+        assert!(contract.reservations_of(accounts(1)).is_empty());
+        assert_eq!(contract.reservations_of(accounts(2)).len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many open reservations for")]
+    fn test_max_locks_per_account() {
+        let (mut context, mut contract, _) = setup();
+        testing_env!(context.attached_deposit(0).build());
+        contract.set_max_locks_per_account(3);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.reserve(accounts(1), U128(1), EXPIRY.into());
+        contract.reserve(accounts(3), U128(1), EXPIRY.into());
+        assert_eq!(contract.reservations_of(accounts(2)).len(), 3);
+        contract.reserve(accounts(4), U128(1), EXPIRY.into());
+    }
+
+    /// Caps the open reservations at one and funds a second sender, accounts(3).
+    fn add_second_sender_with_a_cap_of_one(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+    ) {
+        testing_env!(context.attached_deposit(0).build());
+        contract.set_max_locks_per_account(1);
+        register_account(context, contract, accounts(3));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(3), U128(10), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many open reservations for bob")]
+    fn test_received_reservations_are_capped() {
+        let (mut context, mut contract, _) = setup();
+        add_second_sender_with_a_cap_of_one(&mut context, &mut contract);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.reserve(accounts(1), U128(1), EXPIRY.into());
+    }
+
+    #[test]
+    fn test_rejecting_frees_a_received_slot() {
+        let (mut context, mut contract, id) = setup();
+        add_second_sender_with_a_cap_of_one(&mut context, &mut contract);
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(1))
+            .build());
+        assert_eq!(contract.reject_reservation(id).0, 600);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        contract.reserve(accounts(1), U128(1), EXPIRY.into());
+        assert_eq!(contract.reservations_of(accounts(1)).len(), 1);
+        assert_eq!(contract.reserved_balance_of(accounts(2)).0, 0);
+        assert_eq!(contract.reserved_balance_of(accounts(3)).0, 1);
+    }
+
+    #[test]
+    fn test_closing_the_receiver_releases_the_reservation() {
+        let (mut context, mut contract, id) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        assert!(contract.storage_unregister(None));
+        assert!(contract.get_reservation(id).is_none());
+        assert!(contract.reservations_of(accounts(2)).is_empty());
+        assert_eq!(contract.reserved_balance_of(accounts(2)).0, 0);
+    }
+
+    #[test]
+    fn test_forced_unregister_clears_the_sender_holds() {
+        let (mut context, mut contract, id) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        assert!(contract.storage_unregister(Some(true)));
+        assert!(contract.get_reservation(id).is_none());
+        assert!(contract.reservations_of(accounts(1)).is_empty());
+
+        register_account(&mut context, &mut contract, accounts(2));
+        assert_eq!(contract.reserved_balance_of(accounts(2)).0, 0);
+        assert!(contract.reservations_of(accounts(2)).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Reservation not found")]
+    fn test_double_claim() {
+        let (mut context, mut contract, id) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(0)
+            .predecessor_account_id(accounts(1))
+            .build());
+        contract.claim_reservation(id, None);
+        contract.claim_reservation(id, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough spendable balance")]
+    fn test_reserved_tokens_are_not_spendable() {
+        let (mut context, mut contract, _) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(TOTAL_SUPPLY - 599), None);
+    }
+}
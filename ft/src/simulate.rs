@@ -0,0 +1,168 @@
+//! Dry runs of `ft_transfer` for wallets. `simulate_transfer` runs the same guards as a real
+//! transfer against the current state and reports the first one that would fail. The verdict
+//! of a `compliance_contract` is not part of the simulation, since it is only known
+//! asynchronously.
+use crate::*;
+
+/// Outcome of `simulate_transfer`.
+#[derive(Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SimResult {
+    pub success: bool,
+    /// Message the transfer would panic with.
+    pub error: Option<String>,
+    /// Amount the receiver would get after `transfer_precision` truncation.
+    pub delivered: U128,
+    /// Part of the amount burned from the sender under `RemainderPolicy::Burn`.
+    pub burned: U128,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns whether `sender_id` could `ft_transfer` `amount` to `receiver_id` right now.
+    pub fn simulate_transfer(
+        &self,
+        sender_id: ValidAccountId,
+        receiver_id: ValidAccountId,
+        amount: U128,
+    ) -> SimResult {
+        match self.internal_simulate_transfer(sender_id.as_ref(), receiver_id.as_ref(), amount.0) {
+            Ok((delivered, burned)) => SimResult {
+                success: true,
+                error: None,
+                delivered: delivered.into(),
+                burned: burned.into(),
+            },
+            Err(error) => SimResult {
+                success: false,
+                error: Some(error),
+                delivered: 0.into(),
+                burned: 0.into(),
+            },
+        }
+    }
+}
+
+impl Contract {
+    /// Runs the guards of `ft_transfer` and returns the delivered and burned amounts.
+    fn internal_simulate_transfer(
+        &self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        requested: Balance,
+    ) -> Result<(Balance, Balance), String> {
+        if requested == 0 {
+            return Err("The amount should be a positive number".to_string());
+        }
+        if !self.auto_register_on_receive && !self.token.accounts.contains_key(receiver_id) {
+            return Err(format!("The account {} is not registered", receiver_id));
+        }
+        let amount = self.internal_try_truncate_amount(requested)?;
+        self.check_transfer(sender_id, receiver_id, amount, Clearance::None)?;
+
+        self.check_remainder(sender_id, amount, requested - amount)?;
+        Ok((amount, self.internal_burned_remainder(requested - amount)))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{contract_with_decimals, get_context, register_account, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        (context, contract)
+    }
+
+    fn simulate(contract: &Contract, amount: Balance) -> SimResult {
+        contract.simulate_transfer(accounts(2), accounts(1), U128(amount))
+    }
+
+    #[test]
+    fn test_simulate_passing_transfer() {
+        let (_context, contract) = setup();
+        let result = simulate(&contract, 1_000);
+        assert!(result.success);
+        assert!(result.error.is_none());
+        assert_eq!(result.delivered.0, 1_000);
+        assert_eq!(result.burned.0, 0);
+
+        let result = contract.simulate_transfer(accounts(2), accounts(3), U128(1_000));
+        assert_eq!(result.error, Some(format!("The account {} is not registered", accounts(3))));
+    }
+
+    #[test]
+    fn test_simulate_frozen() {
+        let (_context, mut contract) = setup();
+        contract.freeze_account(accounts(1));
+        let result = simulate(&contract, 1_000);
+        assert!(!result.success);
+        assert_eq!(result.error, Some(format!("Account {} is frozen", accounts(1))));
+        assert_eq!(result.delivered.0, 0);
+
+        contract.unfreeze_account(accounts(1));
+        assert!(simulate(&contract, 1_000).success);
+    }
+
+    #[test]
+    fn test_simulate_limits() {
+        let (_context, mut contract) = setup();
+        contract.set_large_transfer_threshold(Some(U128(1_000)));
+        assert!(simulate(&contract, 1_000).success);
+        assert_eq!(
+            simulate(&contract, 1_001).error.as_deref(),
+            Some("Transfers above the threshold need owner approval")
+        );
+
+        assert_eq!(
+            simulate(&contract, TOTAL_SUPPLY + 1).error.as_deref(),
+            Some("Not enough spendable balance")
+        );
+    }
+
+    #[test]
+    fn test_simulate_block_cooldown() {
+        let (_context, mut contract) = setup();
+        contract.set_max_transfers_per_block(Some(1));
+        assert!(simulate(&contract, 1_000).success);
+        // The simulation does not count as a transfer.
+        assert!(simulate(&contract, 1_000).success);
+
+        contract.ft_transfer(accounts(1), U128(1_000), None);
+        assert_eq!(
+            simulate(&contract, 1_000).error.as_deref(),
+            Some("Too many transfers in this block")
+        );
+    }
+
+    #[test]
+    fn test_simulate_precision_burn() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = contract_with_decimals(4);
+        contract.set_transfer_precision(Some(2), RemainderPolicy::Burn);
+        register_account(&mut context, &mut contract, accounts(1));
+
+        let result = simulate(&contract, 12_345);
+        assert!(result.success);
+        assert_eq!(result.delivered.0, 12_300);
+        assert_eq!(result.burned.0, 45);
+        assert_eq!(
+            simulate(&contract, 99).error.as_deref(),
+            Some("The amount is below the transfer precision")
+        );
+    }
+}
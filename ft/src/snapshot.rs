@@ -0,0 +1,208 @@
+//! Balance snapshots for moving a token to a fresh deployment: `export_balances` pages
+//! through the account index of the old contract and `import_balances` writes the entries
+//! into the new one, initialized with `new_for_import`, until the owner calls `finish_import`.
+use crate::*;
+
+/// Upper bound on the entries handled by one export or import call.
+pub const MAX_SNAPSHOT_BATCH: u64 = 100;
+
+#[near_bindgen]
+impl Contract {
+    /// Returns up to `limit` `(account, balance)` pairs from the account index, starting at
+    /// index `from`. Balances are public, so anyone can read the snapshot.
+    pub fn export_balances(&self, from: U64, limit: u64) -> Vec<(AccountId, U128)> {
+        assert!(limit <= MAX_SNAPSHOT_BATCH, "Too many entries in one batch");
+        let accounts = self.registered_accounts.as_vector();
+        let end = std::cmp::min(from.0.saturating_add(limit), accounts.len());
+        (from.0..end)
+            .map(|index| {
+                let account_id = accounts.get(index).unwrap();
+                let balance = self.token.accounts.get(&account_id).unwrap_or(0);
+                (account_id, balance.into())
+            })
+            .collect()
+    }
+
+    /// Sets the balance of every entry, registering accounts as needed and adjusting the
+    /// total supply. Only accepted by a contract initialized with `new_for_import`. Each change
+    /// is logged as an `ft_mint` or `ft_burn` of the difference. The owner pays for the storage
+    /// of new accounts.
+    #[payable]
+    pub fn import_balances(&mut self, entries: Vec<(ValidAccountId, U128)>) {
+        self.assert_owner();
+        assert!(!self.import_finished, "Balances were already imported");
+        assert!(!self.supply_locked, "Total supply is locked");
+        self.assert_no_supply_split();
+        assert!(entries.len() as u64 <= MAX_SNAPSHOT_BATCH, "Too many entries in one batch");
+        let initial_storage_usage = env::storage_usage();
+        let mut minted: Vec<(AccountId, Balance)> = Vec::new();
+        let mut burned: Vec<(AccountId, Balance)> = Vec::new();
+        for (account_id, balance) in entries.iter() {
+            let account_id: &AccountId = account_id.as_ref();
+            let balance = balance.0;
+            if !self.token.accounts.contains_key(account_id) {
+                self.internal_register_account(account_id);
+            }
+            assert!(
+                balance >= self.reserved.get(account_id).unwrap_or(0),
+                "The balance of {} would drop below its reserved amount",
+                account_id
+            );
+            let old_balance = self.token.accounts.get(account_id).unwrap();
+            self.token.accounts.insert(account_id, &balance);
+            self.internal_track_holder(old_balance, balance);
+            self.token.total_supply = (self.token.total_supply - old_balance)
+                .checked_add(balance)
+                .unwrap_or_else(|| env::panic(b"Total supply overflow"));
+            self.internal_update_twab(account_id);
+            if balance > old_balance {
+                minted.push((account_id.clone(), balance - old_balance));
+            } else if balance < old_balance {
+                burned.push((account_id.clone(), old_balance - balance));
+            }
+        }
+        if let Some(min_total_supply) = self.min_total_supply {
+            assert!(
+                self.token.total_supply >= min_total_supply,
+                "Import would drop the total supply below the minimum"
+            );
+        }
+        let memo = Some("import");
+        if !minted.is_empty() {
+            self.emit_event(Nep141Event::FtMint(
+                minted
+                    .iter()
+                    .map(|(owner_id, amount)| FtMint { owner_id, amount: (*amount).into(), memo })
+                    .collect(),
+            ));
+        }
+        if !burned.is_empty() {
+            self.emit_event(Nep141Event::FtBurn(
+                burned
+                    .iter()
+                    .map(|(owner_id, amount)| FtBurn { owner_id, amount: (*amount).into(), memo })
+                    .collect(),
+            ));
+        }
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+    }
+
+    /// Closes the import for good.
+    pub fn finish_import(&mut self) {
+        self.assert_owner();
+        self.import_finished = true;
+    }
+
+    pub fn is_import_finished(&self) -> bool {
+        self.import_finished
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::convert::TryInto;
+
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn new_for_import() -> Contract {
+        let metadata = FungibleTokenMetadata {
+            spec: FT_METADATA_SPEC.to_string(),
+            name: "BlaBla Token".to_string(),
+            symbol: "BLABLA".to_string(),
+            icon: None,
+            reference: None,
+            reference_hash: None,
+            decimals: 24,
+        };
+        Contract::new_for_import(accounts(2), metadata)
+    }
+
+    #[test]
+    fn test_export_and_import_balances() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        contract.ft_transfer(accounts(3), U128(300), None);
+
+        let mut exported = contract.export_balances(U64(0), 2);
+        exported.extend(contract.export_balances(U64(2), 2));
+        assert_eq!(exported.len(), 3);
+
+        // Mocked storage is per thread, so the new deployment gets a thread of its own.
+        std::thread::spawn(move || {
+            let mut context = get_context(accounts(2));
+            testing_env!(context.build());
+            let mut contract = new_for_import();
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(MINT_DEPOSIT)
+                .build());
+            contract.import_balances(
+                exported
+                    .into_iter()
+                    .map(|(account_id, balance)| (account_id.try_into().unwrap(), balance))
+                    .collect(),
+            );
+            contract.finish_import();
+
+            assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+            assert_eq!(contract.ft_balance_of(accounts(3)).0, 300);
+            assert_eq!(contract.ft_balance_of(accounts(2)).0, TOTAL_SUPPLY - 400);
+            assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+            assert_eq!(contract.holder_count(), 3);
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "Balances were already imported")]
+    fn test_import_after_finish() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = new_for_import();
+        contract.finish_import();
+        contract.import_balances(vec![(accounts(1), U128(10))]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Balances were already imported")]
+    fn test_import_into_regular_contract() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), U128(0));
+        contract.import_balances(vec![(accounts(1), U128(10))]);
+    }
+
+    #[test]
+    fn test_import_logs_the_differences() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = new_for_import();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        contract.import_balances(vec![(accounts(1), U128(100))]);
+        contract.import_balances(vec![(accounts(1), U128(60))]);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 60);
+        assert_eq!(contract.ft_total_supply().0, 60);
+        let logs = get_logs();
+        assert!(logs
+            .iter()
+            .any(|log| log.contains(r#""event":"ft_burn""#) && log.contains(r#""amount":"40""#)));
+    }
+}
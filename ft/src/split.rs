@@ -0,0 +1,335 @@
+//! Stock-split-like rescaling of every balance by `numerator / denominator`. The owner starts
+//! a split with `split_supply` and processes the registered accounts with
+//! `split_supply_batch`; transfers, mints, burns and unregistrations are rejected until the
+//! last batch. The amounts held against an account's balance are rescaled with it: its
+//! reservations, HODL lock and escrow, which is why accounts holding escrow cannot unregister,
+//! and its balances saved for proposal snapshots. The tallies of open proposals are rescaled
+//! when the split starts, and voting waits for the last batch. Large transfer, withdrawal and
+//! burn requests keep their unscaled amounts.
+//!
+//! Resolve callbacks of `ft_transfer_call`, `ft_mint_call`, `melt` and compliance checks carry
+//! the amounts of before the split. The split blocks new ones, and the batches only start once
+//! every callback started before it has run, so no callback sees a partly rescaled state.
+use crate::*;
+
+/// Upper bound on the accounts rescaled by one `split_supply_batch` call.
+pub const MAX_SPLIT_BATCH: u64 = 100;
+
+/// A split in progress.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SupplySplit {
+    pub numerator: u32,
+    pub denominator: u32,
+    /// Index in the account index of the next account to rescale.
+    pub next_index: U64,
+    /// Sum of the balances rescaled so far.
+    pub new_total_supply: U128,
+}
+
+impl SupplySplit {
+    fn scale(&self, amount: Balance) -> Balance {
+        amount * Balance::from(self.numerator) / Balance::from(self.denominator)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Starts rescaling every balance by `numerator / denominator`, rounding down.
+    pub fn split_supply(&mut self, numerator: u32, denominator: u32) {
+        self.assert_owner();
+        assert!(self.supply_split.is_none(), "A supply split is already in progress");
+        assert!(numerator > 0 && denominator > 0, "Split ratio must be positive");
+        assert_ne!(numerator, denominator, "Split ratio must change the supply");
+        assert!(!self.supply_locked || numerator < denominator, "Total supply is locked");
+        self.token
+            .total_supply
+            .checked_mul(Balance::from(numerator))
+            .unwrap_or_else(|| env::panic(b"Split would overflow the total supply"));
+        let split = SupplySplit {
+            numerator,
+            denominator,
+            next_index: 0.into(),
+            new_total_supply: 0.into(),
+        };
+        let now = env::block_timestamp();
+        for (id, open_until) in self.open_proposals.clone() {
+            if now <= open_until {
+                let mut proposal = self.proposals.get(&id).unwrap();
+                proposal.votes_for = split.scale(proposal.votes_for.0).into();
+                proposal.votes_against = split.scale(proposal.votes_against.0).into();
+                self.proposals.insert(&id, &proposal);
+            }
+        }
+        self.supply_split = Some(split);
+    }
+
+    /// Rescales up to `limit` accounts starting at index `from`, which must be the
+    /// `next_index` of the split, and logs the changes as one `ft_mint` or `ft_burn` event.
+    /// Finishes the split after the last account and returns whether it is finished.
+    pub fn split_supply_batch(&mut self, from: U64, limit: u64) -> bool {
+        self.assert_owner();
+        assert!(limit <= MAX_SPLIT_BATCH, "Too many accounts in one batch");
+        let mut split = self.supply_split.take().expect("No supply split in progress");
+        assert_eq!(from.0, split.next_index.0, "Split batch must start at the next index");
+        assert_eq!(
+            self.pending_callbacks, 0,
+            "Callbacks started before the split are still pending"
+        );
+        let accounts = self.registered_accounts.as_vector();
+        let end = std::cmp::min(from.0 + limit, accounts.len());
+        let mut new_total_supply = split.new_total_supply.0;
+        let mut changes: Vec<(AccountId, Balance)> = Vec::new();
+        for index in from.0..end {
+            let account_id = accounts.get(index).unwrap();
+            let balance = self.token.accounts.get(&account_id).unwrap_or(0);
+            let new_balance = split.scale(balance);
+            self.token.accounts.insert(&account_id, &new_balance);
+            self.internal_track_holder(balance, new_balance);
+            self.internal_update_twab(&account_id);
+            self.internal_rescale_holds(&account_id, &split);
+            new_total_supply += new_balance;
+            if new_balance != balance {
+                let change =
+                    std::cmp::max(balance, new_balance) - std::cmp::min(balance, new_balance);
+                changes.push((account_id, change));
+            }
+        }
+        self.internal_log_split_changes(&split, &changes);
+        if end == accounts.len() {
+            self.token.total_supply = new_total_supply;
+            log!(
+                "Supply split {}:{} finished, the total supply is {}",
+                split.numerator,
+                split.denominator,
+                new_total_supply
+            );
+            return true;
+        }
+        split.next_index = end.into();
+        split.new_total_supply = new_total_supply.into();
+        self.supply_split = Some(split);
+        false
+    }
+
+    pub fn get_supply_split(&self) -> Option<SupplySplit> {
+        self.supply_split.clone()
+    }
+}
+
+impl Contract {
+    /// Rescales the amounts held against the balance of `account_id`. `reserved` is rebuilt
+    /// from the rescaled reservations and HODL lock, so it stays their exact sum.
+    fn internal_rescale_holds(&mut self, account_id: &AccountId, split: &SupplySplit) {
+        if let Some(escrowed) = self.escrow_balances.get(account_id) {
+            self.escrow_balances.insert(account_id, &split.scale(escrowed));
+        }
+        if let Some(saved) = self.snapshot_balances.get(account_id) {
+            let rescaled: Vec<(u64, Balance)> =
+                saved.into_iter().map(|(id, balance)| (id, split.scale(balance))).collect();
+            self.snapshot_balances.insert(account_id, &rescaled);
+        }
+        let reserved = match self.reserved.get(account_id) {
+            Some(reserved) => reserved,
+            None => return,
+        };
+        let mut held = 0;
+        let mut new_reserved = 0;
+        for id in self.reservation_ids.get(account_id).unwrap_or_default() {
+            let mut reservation = self.reservations.get(&id).unwrap();
+            held += reservation.amount.0;
+            reservation.amount = split.scale(reservation.amount.0).into();
+            new_reserved += reservation.amount.0;
+            self.reservations.insert(&id, &reservation);
+        }
+        if let Some(mut lock) = self.hodl_locks.get(account_id) {
+            held += lock.amount.0;
+            lock.amount = split.scale(lock.amount.0).into();
+            new_reserved += lock.amount.0;
+            self.hodl_locks.insert(account_id, &lock);
+        }
+        // The rest is held for transfers still waiting for their compliance check.
+        new_reserved += split.scale(reserved - held);
+        if new_reserved == 0 {
+            self.reserved.remove(account_id);
+        } else {
+            self.reserved.insert(account_id, &new_reserved);
+        }
+    }
+
+    fn internal_log_split_changes(&self, split: &SupplySplit, changes: &[(AccountId, Balance)]) {
+        if changes.is_empty() {
+            return;
+        }
+        let memo = Some("supply split");
+        if split.numerator > split.denominator {
+            self.emit_event(Nep141Event::FtMint(
+                changes
+                    .iter()
+                    .map(|(owner_id, amount)| FtMint { owner_id, amount: (*amount).into(), memo })
+                    .collect(),
+            ));
+        } else {
+            self.emit_event(Nep141Event::FtBurn(
+                changes
+                    .iter()
+                    .map(|(owner_id, amount)| FtBurn { owner_id, amount: (*amount).into(), memo })
+                    .collect(),
+            ));
+        }
+    }
+
+    pub(crate) fn assert_no_supply_split(&self) {
+        self.check_no_supply_split().unwrap_or_else(|error| env::panic(error.as_bytes()));
+    }
+
+    pub(crate) fn check_no_supply_split(&self) -> Result<(), String> {
+        match self.supply_split {
+            None => Ok(()),
+            Some(_) => Err("A supply split is in progress".to_string()),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{
+        accounts, get_logs, testing_env_with_promise_results, VMContextBuilder,
+    };
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        contract.ft_transfer(accounts(3), U128(300), None);
+        testing_env!(context.attached_deposit(0).build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_split_supply_in_batches() {
+        let (_context, mut contract) = setup();
+        contract.split_supply(2, 1);
+        assert!(!contract.split_supply_batch(U64(0), 2));
+        assert!(contract.split_supply_batch(U64(2), 2));
+
+        assert!(contract.get_supply_split().is_none());
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 200);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 600);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 2 * (TOTAL_SUPPLY - 400));
+        assert_eq!(contract.ft_total_supply().0, 2 * TOTAL_SUPPLY);
+    }
+
+    #[test]
+    #[should_panic(expected = "A supply split is in progress")]
+    fn test_transfer_during_split() {
+        let (mut context, mut contract) = setup();
+        contract.split_supply(2, 1);
+        contract.split_supply_batch(U64(0), 1);
+
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(1), U128(10), None);
+    }
+
+    fn start_transfer_call_and_split(context: &mut VMContextBuilder, contract: &mut Contract) {
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        contract.ft_transfer_call(accounts(1), U128(100), None, "".to_string());
+        testing_env!(context.attached_deposit(0).build());
+        contract.split_supply(2, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Callbacks started before the split are still pending")]
+    fn test_split_waits_for_pending_callbacks() {
+        let (mut context, mut contract) = setup();
+        start_transfer_call_and_split(&mut context, &mut contract);
+        contract.split_supply_batch(U64(0), 10);
+    }
+
+    #[test]
+    fn test_split_after_callback_resolved() {
+        let (mut context, mut contract) = setup();
+        start_transfer_call_and_split(&mut context, &mut contract);
+        testing_env_with_promise_results(
+            context.storage_usage(env::storage_usage()).predecessor_account_id(accounts(0)).build(),
+            PromiseResult::Successful(b"\"40\"".to_vec()),
+        );
+        contract.ft_resolve_transfer(accounts(2), accounts(1), U128(100));
+
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        assert!(contract.split_supply_batch(U64(0), 10));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 2 * 160);
+        assert_eq!(contract.ft_balance_of(accounts(2)).0, 2 * (TOTAL_SUPPLY - 460));
+        assert_eq!(contract.ft_total_supply().0, 2 * TOTAL_SUPPLY);
+    }
+
+    #[test]
+    fn test_split_rescales_holds() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        let id = contract.reserve(accounts(1), U128(100), U64(1_000));
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(0).build());
+
+        contract.split_supply(1, 2);
+        assert!(contract.split_supply_batch(U64(0), 10));
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 150);
+        assert_eq!(contract.reserved_balance_of(accounts(3)).0, 50);
+        assert_eq!(contract.get_reservation(id).unwrap().amount.0, 50);
+        let logs = get_logs();
+        assert!(logs
+            .iter()
+            .any(|log| log.contains(r#""event":"ft_burn""#) && log.contains("supply split")));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).build());
+        contract.claim_reservation(id, None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 100);
+        assert_eq!(contract.reserved_balance_of(accounts(3)).0, 0);
+    }
+
+    #[test]
+    fn test_split_rescales_open_proposals() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .build());
+        let id = contract.create_proposal("Split the supply".to_string(), U64(1_000));
+        contract.vote(id, false);
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        contract.ft_transfer(accounts(3), U128(50), None);
+
+        testing_env!(context.attached_deposit(0).build());
+        contract.split_supply(2, 1);
+        assert!(contract.split_supply_batch(U64(0), 10));
+        assert_eq!(contract.balance_of_at(accounts(3), id), Some(U128(600)));
+        assert_eq!(contract.balance_of_at(accounts(1), id), Some(U128(200)));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        assert_eq!(contract.vote(id, true).0, 600);
+        let result = contract.proposal_result(id).unwrap();
+        assert_eq!(result.votes_for.0, 600);
+        assert_eq!(result.votes_against.0, 2 * (TOTAL_SUPPLY - 400));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::*;
+
+/// Upper bound on the accounts registered by one `register_accounts_bulk` call.
+pub const MAX_REGISTER_BATCH: usize = 50;
+
+#[near_bindgen]
+impl Contract {
+    /// Registers every account in `accounts` that isn't registered yet, paying their storage
+    /// from the contract balance. Fails before registering anything if the balance or the
+    /// remaining sponsorship budget doesn't cover all of them; the accounts then have to pay
+    /// their own `storage_deposit`. Sponsored accounts paid no storage deposit, so closing one
+    /// refunds nothing and the freed storage stays with the contract. Returns the number of
+    /// accounts registered.
+    pub fn register_accounts_bulk(&mut self, accounts: Vec<ValidAccountId>) -> u32 {
+        self.assert_owner();
+        assert!(accounts.len() <= MAX_REGISTER_BATCH, "Too many accounts in one batch");
+        let mut new_accounts: Vec<AccountId> = accounts
+            .into_iter()
+            .map(|account_id| account_id.into())
+            .filter(|account_id| !self.token.accounts.contains_key(account_id))
+            .collect();
+        new_accounts.sort();
+        new_accounts.dedup();
+
+        let required = Balance::from(self.token.account_storage_usage)
+            * env::storage_byte_cost()
+            * new_accounts.len() as Balance;
+        if let Some(remaining) = self.internal_remaining_sponsorship_budget() {
+            assert!(required <= remaining, "Sponsorship budget exhausted");
+        }
+        let locked = Balance::from(env::storage_usage()) * env::storage_byte_cost();
+        assert!(
+            env::account_balance().saturating_sub(locked) >= required,
+            "Not enough contract balance to sponsor registrations"
+        );
+
+        for account_id in new_accounts.iter() {
+            self.internal_register_account(account_id);
+            self.storage_deposits.insert(account_id, &0);
+        }
+        self.sponsorship_spent += required;
+        new_accounts.len() as u32
+    }
+
+    /// Caps the total storage cost `register_accounts_bulk` may ever sponsor. `None` removes
+    /// the cap. Storage sponsored so far counts against a new budget.
+    pub fn set_sponsorship_budget(&mut self, budget: Option<U128>) {
+        self.assert_owner();
+        self.sponsorship_budget = budget.map(|budget| budget.into());
+    }
+
+    pub fn get_sponsorship_budget(&self) -> Option<U128> {
+        self.sponsorship_budget.map(|budget| budget.into())
+    }
+
+    /// Returns how much sponsorship is left, or `None` without a budget.
+    pub fn remaining_sponsorship_budget(&self) -> Option<U128> {
+        self.internal_remaining_sponsorship_budget().map(|remaining| remaining.into())
+    }
+}
+
+impl Contract {
+    fn internal_remaining_sponsorship_budget(&self) -> Option<Balance> {
+        self.sponsorship_budget.map(|budget| budget.saturating_sub(self.sponsorship_spent))
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_register_accounts_bulk() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        let registered = contract.register_accounts_bulk(vec![
+            accounts(1),
+            accounts(2),
+            accounts(3),
+            accounts(3),
+            accounts(4),
+        ]);
+        assert_eq!(registered, 3);
+        assert!(contract.storage_balance_of(accounts(1)).is_some());
+        assert!(contract.storage_balance_of(accounts(3)).is_some());
+        assert!(contract.storage_balance_of(accounts(4)).is_some());
+    }
+
+    #[test]
+    fn test_sponsored_account_is_not_refunded_the_bond() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        contract.register_accounts_bulk(vec![accounts(1)]);
+        assert_eq!(contract.storage_balance_of(accounts(1)).unwrap().total.0, 0);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(1))
+            .build());
+        assert!(contract.storage_unregister(None));
+        assert!(contract.storage_balance_of(accounts(1)).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough contract balance to sponsor registrations")]
+    fn test_register_accounts_bulk_insufficient_balance() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .account_balance(env::storage_byte_cost() * Balance::from(env::storage_usage()))
+            .build());
+        contract.register_accounts_bulk(vec![accounts(1), accounts(3)]);
+    }
+
+    #[test]
+    fn test_sponsorship_budget() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let registration_cost = contract.storage_balance_bounds().min.0;
+        contract.set_sponsorship_budget(Some(U128(2 * registration_cost)));
+
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        assert_eq!(contract.register_accounts_bulk(vec![accounts(1), accounts(3)]), 2);
+        assert_eq!(contract.remaining_sponsorship_budget(), Some(U128(0)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Sponsorship budget exhausted")]
+    fn test_sponsorship_budget_exhausted() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let registration_cost = contract.storage_balance_bounds().min.0;
+        contract.set_sponsorship_budget(Some(U128(registration_cost)));
+
+        testing_env!(context.storage_usage(env::storage_usage()).build());
+        contract.register_accounts_bulk(vec![accounts(1)]);
+        contract.register_accounts_bulk(vec![accounts(3)]);
+    }
+
+    #[test]
+    fn test_registration_after_budget_exhausted_needs_deposit() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_sponsorship_budget(Some(U128(0)));
+        register_account(&mut context, &mut contract, accounts(1));
+        assert!(contract.storage_balance_of(accounts(1)).is_some());
+    }
+}
@@ -0,0 +1,195 @@
+use near_sdk::serde::Deserialize;
+
+use crate::*;
+
+/// Bytes NEAR charges on top of key and value for every stored record.
+const STORAGE_RECORD_OVERHEAD: StorageUsage = 40;
+/// Borsh size of the longest valid account id: a `u32` length followed by 64 bytes.
+const MAX_ACCOUNT_ID_BORSH_LEN: StorageUsage = 4 + 64;
+
+/// Operations whose storage deposit can be estimated with `estimate_storage_cost`.
+#[derive(Deserialize)]
+#[serde(crate = "near_sdk::serde")]
+pub enum StorageOp {
+    /// `storage_deposit` for a new account.
+    Register,
+    /// `ft_mint` to an unregistered receiver by a first-time minter.
+    Mint,
+    /// `reserve` by an account without other open reservations.
+    Reserve,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns an upper bound of the yoctoNEAR deposit `op` needs for storage, assuming
+    /// account ids of maximum length.
+    pub fn estimate_storage_cost(&self, op: StorageOp) -> U128 {
+        let storage = match op {
+            StorageOp::Register => self.token.account_storage_usage,
+            StorageOp::Mint => {
+                // Balance entry of the receiver plus both records of the minters set entry:
+                // the element vector slot and the element index.
+                let minter_element = 1 + 1 + 8 + MAX_ACCOUNT_ID_BORSH_LEN;
+                let minter_index = 1 + 1 + MAX_ACCOUNT_ID_BORSH_LEN + 8;
+                // The receiver's minted total, its first TWAB checkpoint and checkpoint count.
+                let minted_to = 1 + MAX_ACCOUNT_ID_BORSH_LEN + 16;
+                let twab_checkpoint = 1 + MAX_ACCOUNT_ID_BORSH_LEN + 8 + 8 + 16;
+                let twab_count = 1 + MAX_ACCOUNT_ID_BORSH_LEN + 8;
+                self.token.account_storage_usage
+                    + minter_element
+                    + minter_index
+                    + minted_to
+                    + twab_checkpoint
+                    + twab_count
+                    + 5 * STORAGE_RECORD_OVERHEAD
+            }
+            StorageOp::Reserve => {
+                // The reservation keyed by id, the sender's reserved total and the id lists
+                // of both parties.
+                let reservation = 1 + 8 + 2 * MAX_ACCOUNT_ID_BORSH_LEN + 16 + 8;
+                let reserved_total = 1 + MAX_ACCOUNT_ID_BORSH_LEN + 16;
+                let reservation_ids = 1 + MAX_ACCOUNT_ID_BORSH_LEN + 4 + 8;
+                reservation + reserved_total + 2 * reservation_ids + 4 * STORAGE_RECORD_OVERHEAD
+            }
+        };
+        (env::storage_byte_cost() * Balance::from(storage)).into()
+    }
+
+    /// Returns the deposit `ft_mint` of `amount` to `receiver_id` by `minter_id` needs: the
+    /// mint fee plus the storage of exactly the records that mint creates.
+    pub fn mint_required_deposit(
+        &self,
+        receiver_id: ValidAccountId,
+        amount: U128,
+        minter_id: ValidAccountId,
+    ) -> U128 {
+        assert!(amount.0 <= 1000, "Cannot mint more than 1000 tokens");
+        let receiver_id: &AccountId = receiver_id.as_ref();
+        let minter_id: &AccountId = minter_id.as_ref();
+        let receiver_key = 1 + borsh_len(receiver_id);
+        let mut storage = 0;
+        if !self.token.accounts.contains_key(receiver_id) {
+            storage += receiver_key + 16 + STORAGE_RECORD_OVERHEAD;
+        }
+        if !self.registered_accounts.contains(receiver_id) {
+            // Both records of the account index entry, plus the registration time.
+            storage += 2 + borsh_len(receiver_id) + 8 + STORAGE_RECORD_OVERHEAD;
+            storage += 2 + 8 + borsh_len(receiver_id) + STORAGE_RECORD_OVERHEAD;
+            storage += receiver_key + 8 + STORAGE_RECORD_OVERHEAD;
+        }
+        if self.minted_to.get(receiver_id).is_none() {
+            storage += receiver_key + 16 + STORAGE_RECORD_OVERHEAD;
+        }
+        if !self.minters.contains(minter_id) {
+            storage += 2 + borsh_len(minter_id) + 8 + STORAGE_RECORD_OVERHEAD;
+            storage += 2 + 8 + borsh_len(minter_id) + STORAGE_RECORD_OVERHEAD;
+        }
+        // The mint lands in a later block, so it adds a TWAB checkpoint unless the ring is full.
+        let twab_count = self.twab_counts.get(receiver_id);
+        let twab_capacity = self.twab_capacities.get(receiver_id).unwrap_or(1);
+        if twab_count.map_or(true, |count| count < twab_capacity) {
+            storage += receiver_key + 8 + 8 + 16 + STORAGE_RECORD_OVERHEAD;
+        }
+        if twab_count.is_none() {
+            storage += receiver_key + 8 + STORAGE_RECORD_OVERHEAD;
+        }
+        let fee = if self.internal_is_system_account(minter_id) { 0 } else { self.mint_fee_yocto };
+        (env::storage_byte_cost() * Balance::from(storage) + fee).into()
+    }
+}
+
+/// Borsh size of an account id: a `u32` length followed by its bytes.
+fn borsh_len(account_id: &AccountId) -> StorageUsage {
+    4 + account_id.len() as StorageUsage
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::accounts;
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_estimate_storage_cost_is_positive() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        let register = contract.estimate_storage_cost(StorageOp::Register).0;
+        let mint = contract.estimate_storage_cost(StorageOp::Mint).0;
+        let reserve = contract.estimate_storage_cost(StorageOp::Reserve).0;
+        assert!(register > 0);
+        assert!(mint > register);
+        assert!(reserve > 0);
+    }
+
+    #[test]
+    fn test_estimate_covers_actual_usage() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .build());
+        let before = env::storage_usage();
+        contract.ft_mint(accounts(4).into(), U128(10));
+        let used = env::storage_byte_cost() * Balance::from(env::storage_usage() - before);
+        assert!(used <= contract.estimate_storage_cost(StorageOp::Mint).0);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .predecessor_account_id(accounts(2))
+            .build());
+        let before = env::storage_usage();
+        contract.reserve(accounts(1), U128(10), U64(1_000));
+        let used = env::storage_byte_cost() * Balance::from(env::storage_usage() - before);
+        assert!(used <= contract.estimate_storage_cost(StorageOp::Reserve).0);
+    }
+
+    fn mint_cost(contract: &mut Contract, receiver_id: ValidAccountId) -> Balance {
+        let before = env::storage_usage();
+        contract.ft_mint(receiver_id.into(), U128(10));
+        env::storage_byte_cost() * Balance::from(env::storage_usage() - before)
+    }
+
+    #[test]
+    fn test_mint_required_deposit_for_new_receiver() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_mint_fee(U128(7));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(1)
+            .build());
+        let required = contract.mint_required_deposit(accounts(4), U128(10), accounts(3)).0;
+        assert_eq!(required, mint_cost(&mut contract, accounts(4)) + 7);
+    }
+
+    #[test]
+    fn test_mint_required_deposit_for_registered_receiver() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(3))
+            .block_timestamp(1)
+            .build());
+        let new_receiver = contract.mint_required_deposit(accounts(4), U128(10), accounts(3)).0;
+        let registered = contract.mint_required_deposit(accounts(1), U128(10), accounts(3)).0;
+        assert!(registered < new_receiver);
+        assert_eq!(registered, mint_cost(&mut contract, accounts(1)));
+    }
+}
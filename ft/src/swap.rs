@@ -0,0 +1,134 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Exchanges the balances of two registered accounts, for merges and corrections. The
+    /// total supply is unchanged. Each account must keep enough tokens for its reservations.
+    /// The contract account is excluded, since it holds the escrowed tokens. A swap that takes
+    /// more than the withdrawal threshold from the owner or the treasury is rejected, since
+    /// such withdrawals must be queued. Indexers see the net change as one `ft_transfer`.
+    pub fn admin_swap_balances(&mut self, account_a: ValidAccountId, account_b: ValidAccountId) {
+        self.assert_owner();
+        self.assert_no_supply_split();
+        let account_a: AccountId = account_a.into();
+        let account_b: AccountId = account_b.into();
+        assert_ne!(account_a, account_b, "Accounts should be different");
+        let contract_id = env::current_account_id();
+        assert!(
+            account_a != contract_id && account_b != contract_id,
+            "Cannot swap the balance of the contract account"
+        );
+        let balance_a = self.token.internal_unwrap_balance_of(&account_a);
+        let balance_b = self.token.internal_unwrap_balance_of(&account_b);
+        assert!(
+            self.reserved.get(&account_a).unwrap_or(0) <= balance_b
+                && self.reserved.get(&account_b).unwrap_or(0) <= balance_a,
+            "Swap would leave reserved tokens uncovered"
+        );
+        let (old_owner_id, new_owner_id, net_amount) = if balance_a > balance_b {
+            (&account_a, &account_b, balance_a - balance_b)
+        } else {
+            (&account_b, &account_a, balance_b - balance_a)
+        };
+        self.assert_below_withdrawal_threshold(old_owner_id, net_amount);
+
+        self.token.accounts.insert(&account_a, &balance_b);
+        self.token.accounts.insert(&account_b, &balance_a);
+        self.internal_update_twab(&account_a);
+        self.internal_update_twab(&account_b);
+        if net_amount > 0 {
+            self.emit_event(Nep141Event::FtTransfer(vec![FtTransfer {
+                old_owner_id,
+                new_owner_id,
+                amount: net_amount.into(),
+                memo: Some("balance swap"),
+            }]));
+        }
+        self.emit_contract_event(ContractEvent::BalancesSwapped(vec![BalancesSwapped {
+            owner_id: &self.owner_id,
+            account_a: &account_a,
+            account_b: &account_b,
+            balance_a: balance_b.into(),
+            balance_b: balance_a.into(),
+        }]));
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, get_logs};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, TOTAL_SUPPLY};
+
+    #[test]
+    fn test_admin_swap_balances() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(100), None);
+        contract.ft_transfer(accounts(3), U128(300), None);
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(0).build());
+        contract.admin_swap_balances(accounts(1), accounts(3));
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 300);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 100);
+        assert_eq!(contract.ft_total_supply().0, TOTAL_SUPPLY);
+        assert_eq!(contract.holder_count(), 3);
+        assert_eq!(
+            get_logs(),
+            vec![
+                format!(
+                    r#"EVENT_JSON:{{"standard":"nep141","version":"1.0.0","event":"ft_transfer","data":[{{"old_owner_id":"{}","new_owner_id":"{}","amount":"200","memo":"balance swap"}}]}}"#,
+                    accounts(3).as_ref(),
+                    accounts(1).as_ref()
+                ),
+                format!(
+                    r#"EVENT_JSON:{{"standard":"fungible_token","version":"1.0.0","event":"balances_swapped","data":[{{"owner_id":"{}","account_a":"{}","account_b":"{}","balance_a":"300","balance_b":"100"}}]}}"#,
+                    accounts(2).as_ref(),
+                    accounts(1).as_ref(),
+                    accounts(3).as_ref()
+                )
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers above the withdrawal threshold must be queued")]
+    fn test_admin_swap_above_withdrawal_threshold() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        testing_env!(context.predecessor_account_id(accounts(2)).build());
+        contract.set_withdrawal_threshold(Some(U128(1_000)));
+        contract.admin_swap_balances(accounts(2), accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "is not registered")]
+    fn test_admin_swap_with_unregistered_account() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.admin_swap_balances(accounts(2), accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot swap the balance of the contract account")]
+    fn test_admin_swap_with_contract_account() {
+        let context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.admin_swap_balances(accounts(2), accounts(0));
+    }
+}
@@ -0,0 +1,113 @@
+use crate::*;
+
+#[near_bindgen]
+impl Contract {
+    /// Exempts `account_id` from freezes, account rules, the mint fee, the account age and
+    /// per-block limits, and the dust and large transfer thresholds.
+    pub fn add_system_account(&mut self, account_id: ValidAccountId) {
+        self.assert_owner();
+        self.system_accounts.insert(account_id.as_ref());
+    }
+
+    pub fn remove_system_account(&mut self, account_id: ValidAccountId) {
+        self.assert_owner();
+        self.system_accounts.remove(account_id.as_ref());
+    }
+
+    pub fn is_system_account(&self, account_id: ValidAccountId) -> bool {
+        self.internal_is_system_account(account_id.as_ref())
+    }
+
+    pub fn get_system_accounts(&self) -> Vec<AccountId> {
+        self.system_accounts.to_vec()
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_is_system_account(&self, account_id: &AccountId) -> bool {
+        self.system_accounts.contains(account_id)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        register_account(&mut context, &mut contract, accounts(3));
+        contract.set_large_transfer_threshold(Some(U128(1_000)));
+        contract.set_max_transfers_per_block(Some(1));
+        contract.set_min_account_balance(Some(U128(100)));
+        contract.freeze_account(accounts(3));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .build());
+        contract.ft_transfer(accounts(1), U128(1_000), None);
+        (context, contract)
+    }
+
+    #[test]
+    fn test_system_account_bypasses_restrictions() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(0).build());
+        contract.add_system_account(accounts(1));
+        contract.add_system_account(accounts(3));
+        assert!(contract.is_system_account(accounts(1)));
+
+        // Frozen receiver, several transfers in one block and a balance left under the
+        // minimum.
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.ft_transfer(accounts(3), U128(500), None);
+        contract.ft_transfer(accounts(3), U128(450), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 50);
+        assert_eq!(contract.ft_balance_of(accounts(3)).0, 950);
+
+        // Above the large transfer threshold.
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(0).build());
+        contract.add_system_account(accounts(2));
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(1), U128(5_000), None);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 5_050);
+    }
+
+    #[test]
+    #[should_panic(expected = "Too many transfers in this block")]
+    fn test_removed_system_account_is_limited_again() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(0).build());
+        contract.add_system_account(accounts(1));
+        contract.remove_system_account(accounts(1));
+
+        testing_env!(context.predecessor_account_id(accounts(1)).attached_deposit(1).build());
+        contract.ft_transfer(accounts(2), U128(100), None);
+        contract.ft_transfer(accounts(2), U128(100), None);
+    }
+
+    #[test]
+    fn test_system_account_mints_without_fee() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.predecessor_account_id(accounts(2)).attached_deposit(0).build());
+        contract.set_mint_fee(U128(MINT_DEPOSIT));
+        contract.add_system_account(accounts(4));
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(4))
+            .build());
+        contract.ft_mint(accounts(4).into(), U128(10));
+        assert_eq!(contract.ft_balance_of(accounts(4)).0, 10);
+    }
+}
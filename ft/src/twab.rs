@@ -0,0 +1,352 @@
+//! Time-weighted average balances.
+//!
+//! Every balance change appends a `(timestamp, balance)` checkpoint for the account, or
+//! overwrites the last one within the same block. Averages are integrated over the checkpoints
+//! instead of keeping a running `balance * time` sum, since that product overflows `u128` for
+//! 24-decimal supplies within days.
+//!
+//! Only the last checkpoints of an account are kept, in a ring keyed by the checkpoint index
+//! modulo the ring capacity. The registration deposit, or the storage payment of the mint that
+//! creates the account, pays for a ring of one checkpoint, and `extend_balance_history` lets the
+//! holder pay for up to `MAX_TWAB_CHECKPOINTS`, so transfers, which only attach 1 yoctoNEAR,
+//! never add unpaid storage. Queries reaching back before the oldest kept checkpoint return
+//! `None`.
+use crate::*;
+
+/// Largest ring of checkpoints an account can pay for.
+pub const MAX_TWAB_CHECKPOINTS: u64 = 16;
+
+/// Computes `a * b / c` without overflow, given `b <= c`.
+fn mul_div(a: u128, b: u64, c: u64) -> u128 {
+    let (b, c) = (b as u128, c as u128);
+    a / c * b + a % c * b / c
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns the average balance of the account between `since_timestamp` and now, or
+    /// `None` if the checkpoints since then are no longer kept.
+    pub fn time_weighted_balance(
+        &self,
+        account_id: ValidAccountId,
+        since_timestamp: U64,
+    ) -> Option<U128> {
+        let account_id: AccountId = account_id.into();
+        let now = env::block_timestamp();
+        let since = since_timestamp.0;
+        if since >= now {
+            return Some(self.token.accounts.get(&account_id).unwrap_or(0).into());
+        }
+        let count = self.twab_counts.get(&account_id).unwrap_or(0);
+        let capacity = self.internal_twab_capacity(&account_id);
+        let low = self.internal_first_twab_checkpoint_after(&account_id, since, count);
+        if !is_twab_history_kept(low, count, capacity) {
+            return None;
+        }
+
+        let period = now - since;
+        let mut balance =
+            if low == 0 { 0 } else { self.internal_twab_checkpoint(&account_id, low - 1).1 };
+        let mut cursor = since;
+        let mut total = 0;
+        for index in low..count {
+            let (timestamp, next_balance) = self.internal_twab_checkpoint(&account_id, index);
+            total += mul_div(balance, timestamp - cursor, period);
+            cursor = timestamp;
+            balance = next_balance;
+        }
+        total += mul_div(balance, now - cursor, period);
+        Some(total.into())
+    }
+
+    /// Returns the balance the account held at block timestamp `timestamp`, or `None` if the
+    /// checkpoints back then are no longer kept.
+    pub fn balance_at_time(&self, account_id: ValidAccountId, timestamp: U64) -> Option<U128> {
+        self.internal_balance_at(account_id.as_ref(), timestamp.0).map(U128)
+    }
+
+    /// Returns how many checkpoints of the account are kept.
+    pub fn balance_history_capacity(&self, account_id: ValidAccountId) -> U64 {
+        self.internal_twab_capacity(account_id.as_ref()).into()
+    }
+
+    /// Keeps the last `capacity` checkpoints of the caller, at most `MAX_TWAB_CHECKPOINTS`,
+    /// instead of fewer. The attached deposit pays for the new ring slots and is refunded with
+    /// the storage deposit when the account closes.
+    #[payable]
+    pub fn extend_balance_history(&mut self, capacity: U64) {
+        let initial_storage_usage = env::storage_usage();
+        let account_id = env::predecessor_account_id();
+        let capacity = capacity.0;
+        assert!(
+            self.token.accounts.contains_key(&account_id),
+            "The account {} is not registered",
+            account_id
+        );
+        let old_capacity = self.internal_twab_capacity(&account_id);
+        assert!(
+            old_capacity < capacity && capacity <= MAX_TWAB_CHECKPOINTS,
+            "The capacity must be above {} and at most {}",
+            old_capacity,
+            MAX_TWAB_CHECKPOINTS
+        );
+
+        // Lays the kept checkpoints out for the new capacity and fills the other slots, so
+        // later checkpoints only overwrite paid storage.
+        let count = self.twab_counts.get(&account_id).unwrap_or(0);
+        let kept: Vec<(u64, (u64, Balance))> = (count.saturating_sub(old_capacity)..count)
+            .map(|index| (index, self.internal_twab_checkpoint(&account_id, index)))
+            .collect();
+        for slot in 0..capacity {
+            self.twab_checkpoints.insert(&(account_id.clone(), slot), &(0, 0));
+        }
+        for (index, checkpoint) in kept {
+            self.twab_checkpoints.insert(&(account_id.clone(), index % capacity), &checkpoint);
+        }
+        self.twab_capacities.insert(&account_id, &capacity);
+
+        let deposit = self.internal_storage_deposit_of(&account_id);
+        self.storage_deposits.insert(&account_id, &deposit);
+        let storage_used = env::storage_usage() - initial_storage_usage;
+        let cost = Balance::from(storage_used) * env::storage_byte_cost();
+        self.storage_deposits.insert(&account_id, &(deposit + cost));
+        refund_deposit(storage_used);
+    }
+}
+
+/// Whether the balance before checkpoint `index` is still kept, out of `count` checkpoints
+/// taken into a ring of `capacity`.
+fn is_twab_history_kept(index: u64, count: u64, capacity: u64) -> bool {
+    index == 0 || index > count.saturating_sub(capacity)
+}
+
+impl Contract {
+    /// Checkpoints kept for the account: one unless it paid for more.
+    fn internal_twab_capacity(&self, account_id: &AccountId) -> u64 {
+        self.twab_capacities.get(account_id).unwrap_or(1)
+    }
+
+    fn internal_twab_checkpoint(&self, account_id: &AccountId, index: u64) -> (u64, Balance) {
+        let slot = index % self.internal_twab_capacity(account_id);
+        self.twab_checkpoints.get(&(account_id.clone(), slot)).unwrap()
+    }
+
+    /// Index of the first of the kept checkpoints taken after `timestamp`, out of `count`
+    /// checkpoints ever taken.
+    fn internal_first_twab_checkpoint_after(
+        &self,
+        account_id: &AccountId,
+        timestamp: u64,
+        count: u64,
+    ) -> u64 {
+        let capacity = self.internal_twab_capacity(account_id);
+        let (mut low, mut high) = (count.saturating_sub(capacity), count);
+        while low < high {
+            let mid = (low + high) / 2;
+            if self.internal_twab_checkpoint(account_id, mid).0 <= timestamp {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    /// Returns the balance the account held at `timestamp`, from its checkpoints, or `None`
+    /// if they were overwritten since.
+    fn internal_balance_at(&self, account_id: &AccountId, timestamp: u64) -> Option<Balance> {
+        let count = self.twab_counts.get(account_id).unwrap_or(0);
+        let capacity = self.internal_twab_capacity(account_id);
+        let index = self.internal_first_twab_checkpoint_after(account_id, timestamp, count);
+        if !is_twab_history_kept(index, count, capacity) {
+            return None;
+        }
+        match index {
+            0 => Some(0),
+            index => Some(self.internal_twab_checkpoint(account_id, index - 1).1),
+        }
+    }
+
+    /// Records the current balance of `account_id`, and the one before the change for the
+    /// snapshots of open proposals. Call after every balance change.
+    pub(crate) fn internal_update_twab(&mut self, account_id: &AccountId) {
+        let now = env::block_timestamp();
+        let balance = self.token.accounts.get(account_id).unwrap_or(0);
+        let count = self.twab_counts.get(account_id).unwrap_or(0);
+        let capacity = self.internal_twab_capacity(account_id);
+        let previous_balance =
+            if count == 0 { 0 } else { self.internal_twab_checkpoint(account_id, count - 1).1 };
+        self.internal_record_snapshot_balance(account_id, previous_balance);
+        if count > 0 && self.internal_twab_checkpoint(account_id, count - 1).0 == now {
+            let slot = (count - 1) % capacity;
+            self.twab_checkpoints.insert(&(account_id.clone(), slot), &(now, balance));
+        } else {
+            let slot = count % capacity;
+            self.twab_checkpoints.insert(&(account_id.clone(), slot), &(now, balance));
+            self.twab_counts.insert(account_id, &(count + 1));
+        }
+    }
+
+    /// Removes the checkpoints of a closed account.
+    pub(crate) fn internal_clear_twab(&mut self, account_id: &AccountId) {
+        let capacity = self.internal_twab_capacity(account_id);
+        self.twab_counts.remove(account_id);
+        self.twab_capacities.remove(account_id);
+        for slot in 0..capacity {
+            self.twab_checkpoints.remove(&(account_id.clone(), slot));
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn extend_history(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        account_id: ValidAccountId,
+        capacity: u64,
+    ) {
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(account_id)
+            .build());
+        contract.extend_balance_history(U64(capacity));
+    }
+
+    #[test]
+    fn test_mul_div() {
+        assert_eq!(mul_div(u128::MAX, u64::MAX, u64::MAX), u128::MAX);
+        assert_eq!(mul_div(10, 1, 4), 2);
+    }
+
+    #[test]
+    fn test_time_weighted_balance() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        extend_history(&mut context, &mut contract, accounts(1), 4);
+        extend_history(&mut context, &mut contract, accounts(2), 4);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(100)
+            .build());
+        contract.ft_transfer(accounts(1), U128(TOTAL_SUPPLY / 2), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .block_timestamp(200)
+            .build());
+        contract.ft_transfer(accounts(1), U128(TOTAL_SUPPLY / 2), None);
+
+        testing_env!(context.is_view(true).block_timestamp(400).build());
+        let twab = |account_id, since| contract.time_weighted_balance(account_id, U64(since));
+        // Held everything for 100ns, half for 100ns and nothing for 200ns.
+        assert_eq!(twab(accounts(2), 0), Some(U128(TOTAL_SUPPLY * 3 / 8)));
+        assert_eq!(twab(accounts(1), 0), Some(U128(TOTAL_SUPPLY * 5 / 8)));
+        assert_eq!(twab(accounts(1), 150), Some(U128(TOTAL_SUPPLY * 9 / 10)));
+        assert_eq!(twab(accounts(1), 200), Some(U128(TOTAL_SUPPLY)));
+        assert_eq!(twab(accounts(3), 0), Some(U128(0)));
+    }
+
+    #[test]
+    fn test_default_history_keeps_the_last_checkpoint() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+
+        for step in 1..=2 {
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(1)
+                .predecessor_account_id(accounts(2))
+                .block_timestamp(step * 100)
+                .build());
+            let storage_before = env::storage_usage();
+            contract.ft_transfer(accounts(1), U128(1), None);
+            if step > 1 {
+                assert_eq!(env::storage_usage(), storage_before);
+            }
+        }
+        assert_eq!(contract.balance_at_time(accounts(1), U64(250)), Some(U128(2)));
+        assert_eq!(contract.balance_at_time(accounts(1), U64(150)), None);
+        assert_eq!(contract.time_weighted_balance(accounts(1), U64(150)), None);
+    }
+
+    fn fill_checkpoints() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        extend_history(&mut context, &mut contract, accounts(1), MAX_TWAB_CHECKPOINTS);
+
+        for step in 1..=MAX_TWAB_CHECKPOINTS + 1 {
+            testing_env!(context
+                .storage_usage(env::storage_usage())
+                .attached_deposit(1)
+                .predecessor_account_id(accounts(2))
+                .block_timestamp(step * 100)
+                .build());
+            let storage_before = env::storage_usage();
+            contract.ft_transfer(accounts(1), U128(1), None);
+            if step > 1 {
+                assert_eq!(env::storage_usage(), storage_before);
+            }
+        }
+        (context, contract)
+    }
+
+    #[test]
+    fn test_twab_checkpoints_are_capped() {
+        let (_, contract) = fill_checkpoints();
+        let last = (MAX_TWAB_CHECKPOINTS + 1) * 100;
+        assert_eq!(
+            contract.balance_at_time(accounts(1), U64(last)),
+            Some(U128(MAX_TWAB_CHECKPOINTS as u128 + 1))
+        );
+        assert_eq!(contract.balance_at_time(accounts(1), U64(250)), Some(U128(2)));
+        assert_eq!(contract.balance_at_time(accounts(1), U64(150)), None);
+    }
+
+    #[test]
+    fn test_extended_history_is_part_of_the_storage_deposit() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        register_account(&mut context, &mut contract, accounts(1));
+        let storage_before = env::storage_usage();
+        extend_history(&mut context, &mut contract, accounts(1), 4);
+        let cost = Balance::from(env::storage_usage() - storage_before) * env::storage_byte_cost();
+        assert_eq!(contract.balance_history_capacity(accounts(1)).0, 4);
+        assert_eq!(
+            contract.storage_balance_of(accounts(1)).unwrap().total.0,
+            contract.storage_balance_bounds().min.0 + cost
+        );
+
+        testing_env!(context.storage_usage(env::storage_usage()).attached_deposit(1).build());
+        assert!(contract.storage_unregister(None));
+        assert_eq!(contract.balance_history_capacity(accounts(1)).0, 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "The capacity must be above 1 and at most 16")]
+    fn test_extend_history_above_cap() {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        extend_history(&mut context, &mut contract, accounts(2), MAX_TWAB_CHECKPOINTS + 1);
+    }
+}
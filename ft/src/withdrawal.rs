@@ -0,0 +1,335 @@
+//! Drain protection: transfers from the owner or the treasury above `withdrawal_threshold`
+//! must be queued and can only be executed after `withdrawal_delay`. Raising or removing the
+//! threshold and shortening the delay only apply once the current delay has passed, so the
+//! owner cannot switch the protection off right before a drain.
+use crate::*;
+
+/// Default time between queueing and executing a withdrawal, in nanoseconds.
+pub const DEFAULT_WITHDRAWAL_DELAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Withdrawal {
+    pub sender_id: AccountId,
+    pub receiver_id: AccountId,
+    pub amount: U128,
+    pub memo: Option<String>,
+    /// Block timestamp in nanoseconds from which the withdrawal can be executed.
+    pub executable_at: U64,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Queues a transfer from the owner or treasury and returns its id. The caller pays for
+    /// the storage of the withdrawal.
+    #[payable]
+    pub fn queue_withdrawal(
+        &mut self,
+        receiver_id: ValidAccountId,
+        amount: U128,
+        memo: Option<String>,
+    ) -> U64 {
+        let initial_storage_usage = env::storage_usage();
+        let sender_id = env::predecessor_account_id();
+        assert!(
+            self.is_drain_protected(&sender_id),
+            "Only the owner or the treasury can queue withdrawals"
+        );
+        assert!(amount.0 > 0, "The amount should be a positive number");
+        let id = self.next_withdrawal_id;
+        self.next_withdrawal_id += 1;
+        self.withdrawals.insert(
+            &id,
+            &Withdrawal {
+                sender_id,
+                receiver_id: receiver_id.into(),
+                amount,
+                memo,
+                executable_at: (env::block_timestamp() + self.internal_withdrawal_delay()).into(),
+            },
+        );
+        refund_deposit(env::storage_usage() - initial_storage_usage);
+        id.into()
+    }
+
+    /// Executes a queued withdrawal once its delay has passed. Other transfer restrictions
+    /// still apply.
+    #[payable]
+    pub fn execute_withdrawal(&mut self, id: U64) {
+        assert_one_yocto();
+        let withdrawal = self.withdrawals.get(&id.0).expect("Withdrawal not found");
+        assert_eq!(
+            env::predecessor_account_id(),
+            withdrawal.sender_id,
+            "Only the sender can execute the withdrawal"
+        );
+        assert!(
+            env::block_timestamp() >= withdrawal.executable_at.0,
+            "Withdrawal delay has not passed"
+        );
+        self.withdrawals.remove(&id.0);
+        self.internal_cleared_transfer(
+            &withdrawal.sender_id,
+            &withdrawal.receiver_id,
+            withdrawal.amount.into(),
+            withdrawal.memo,
+            Clearance::Withdrawal,
+        );
+    }
+
+    /// Drops a queued withdrawal. Callable by its sender or the owner.
+    pub fn cancel_withdrawal(&mut self, id: U64) {
+        let withdrawal = self.withdrawals.get(&id.0).expect("Withdrawal not found");
+        let predecessor_id = env::predecessor_account_id();
+        assert!(
+            predecessor_id == withdrawal.sender_id || predecessor_id == self.owner_id,
+            "Only the sender or the owner can cancel the withdrawal"
+        );
+        self.withdrawals.remove(&id.0);
+    }
+
+    pub fn get_withdrawal(&self, id: U64) -> Option<Withdrawal> {
+        self.withdrawals.get(&id.0)
+    }
+
+    /// Sets the amount above which owner and treasury transfers must be queued. `None`
+    /// disables the delay. A higher threshold or `None` applies after the current delay.
+    pub fn set_withdrawal_threshold(&mut self, threshold: Option<U128>) {
+        self.assert_owner();
+        self.internal_set_withdrawal_threshold(threshold.map(|threshold| threshold.into()));
+    }
+
+    pub fn get_withdrawal_threshold(&self) -> Option<U128> {
+        self.internal_withdrawal_threshold().map(|threshold| threshold.into())
+    }
+
+    /// Sets the delay for withdrawals queued from now on, in nanoseconds. While a threshold is
+    /// set, a shorter delay applies after the current one.
+    pub fn set_withdrawal_delay(&mut self, delay: U64) {
+        self.assert_owner();
+        self.internal_set_withdrawal_delay(delay.0);
+    }
+
+    pub fn get_withdrawal_delay(&self) -> U64 {
+        self.internal_withdrawal_delay().into()
+    }
+
+    /// Returns the loosened threshold waiting to apply and the block timestamp it applies from.
+    pub fn get_pending_withdrawal_threshold(&self) -> Option<(Option<U128>, U64)> {
+        self.pending_withdrawal_threshold
+            .filter(|(_, applies_at)| env::block_timestamp() < *applies_at)
+            .map(|(threshold, applies_at)| (threshold.map(|t| t.into()), applies_at.into()))
+    }
+
+    /// Returns the shortened delay waiting to apply and the block timestamp it applies from.
+    pub fn get_pending_withdrawal_delay(&self) -> Option<(U64, U64)> {
+        self.pending_withdrawal_delay
+            .filter(|(_, applies_at)| env::block_timestamp() < *applies_at)
+            .map(|(delay, applies_at)| (delay.into(), applies_at.into()))
+    }
+}
+
+impl Contract {
+    pub(crate) fn internal_withdrawal_threshold(&self) -> Option<Balance> {
+        match self.pending_withdrawal_threshold {
+            Some((threshold, applies_at)) if env::block_timestamp() >= applies_at => threshold,
+            _ => self.withdrawal_threshold,
+        }
+    }
+
+    pub(crate) fn internal_withdrawal_delay(&self) -> u64 {
+        match self.pending_withdrawal_delay {
+            Some((delay, applies_at)) if env::block_timestamp() >= applies_at => delay,
+            _ => self.withdrawal_delay,
+        }
+    }
+
+    /// Applies a lower threshold right away and holds back a higher one or `None` until the
+    /// current delay has passed. Either replaces a loosening still waiting.
+    pub(crate) fn internal_set_withdrawal_threshold(&mut self, threshold: Option<Balance>) {
+        let current = self.internal_withdrawal_threshold();
+        self.withdrawal_threshold = current;
+        let loosens = match (current, threshold) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(current), Some(threshold)) => threshold > current,
+        };
+        if loosens {
+            let applies_at = env::block_timestamp() + self.internal_withdrawal_delay();
+            self.pending_withdrawal_threshold = Some((threshold, applies_at));
+        } else {
+            self.withdrawal_threshold = threshold;
+            self.pending_withdrawal_threshold = None;
+        }
+    }
+
+    /// Applies a longer delay right away and, while a threshold is set, holds back a shorter
+    /// one until the current delay has passed. Either replaces a shortening still waiting.
+    pub(crate) fn internal_set_withdrawal_delay(&mut self, delay: u64) {
+        let current = self.internal_withdrawal_delay();
+        self.withdrawal_delay = current;
+        if delay < current && self.internal_withdrawal_threshold().is_some() {
+            self.pending_withdrawal_delay = Some((delay, env::block_timestamp() + current));
+        } else {
+            self.withdrawal_delay = delay;
+            self.pending_withdrawal_delay = None;
+        }
+    }
+
+    fn is_drain_protected(&self, account_id: &AccountId) -> bool {
+        account_id == &self.owner_id || account_id == &self.treasury_id
+    }
+
+    pub(crate) fn assert_below_withdrawal_threshold(&self, sender_id: &AccountId, amount: Balance) {
+        self.check_below_withdrawal_threshold(sender_id, amount)
+            .unwrap_or_else(|error| env::panic(error.as_bytes()));
+    }
+
+    pub(crate) fn check_below_withdrawal_threshold(
+        &self,
+        sender_id: &AccountId,
+        amount: Balance,
+    ) -> Result<(), String> {
+        match self.internal_withdrawal_threshold() {
+            Some(threshold) if amount > threshold && self.is_drain_protected(sender_id) => {
+                Err("Transfers above the withdrawal threshold must be queued".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+    use near_sdk::MockedBlockchain;
+
+    use super::*;
+    use crate::tests::{get_context, register_account, MINT_DEPOSIT, TOTAL_SUPPLY};
+
+    fn setup() -> (VMContextBuilder, Contract) {
+        let mut context = get_context(accounts(2));
+        testing_env!(context.build());
+        let mut contract = Contract::new_default_meta(accounts(2).into(), TOTAL_SUPPLY.into());
+        contract.set_withdrawal_delay(U64(100));
+        contract.set_withdrawal_threshold(Some(U128(1_000)));
+        register_account(&mut context, &mut contract, accounts(1));
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(MINT_DEPOSIT)
+            .predecessor_account_id(accounts(2))
+            .block_timestamp(1_000)
+            .build());
+        (context, contract)
+    }
+
+    #[test]
+    fn test_delayed_withdrawal() {
+        let (mut context, mut contract) = setup();
+        let id = contract.queue_withdrawal(accounts(1), U128(5_000), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .block_timestamp(1_100)
+            .build());
+        contract.execute_withdrawal(id);
+        assert_eq!(contract.ft_balance_of(accounts(1)).0, 5_000);
+        assert!(contract.get_withdrawal(id).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Withdrawal delay has not passed")]
+    fn test_premature_withdrawal() {
+        let (mut context, mut contract) = setup();
+        let id = contract.queue_withdrawal(accounts(1), U128(5_000), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .block_timestamp(1_099)
+            .build());
+        contract.execute_withdrawal(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Withdrawal not found")]
+    fn test_cancelled_withdrawal() {
+        let (mut context, mut contract) = setup();
+        let id = contract.queue_withdrawal(accounts(1), U128(5_000), None);
+        contract.cancel_withdrawal(id);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .block_timestamp(1_100)
+            .build());
+        contract.execute_withdrawal(id);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers above the withdrawal threshold must be queued")]
+    fn test_direct_owner_transfer_above_threshold() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer(accounts(1), U128(5_000), None);
+    }
+
+    #[test]
+    fn test_loosening_waits_out_the_delay() {
+        let (mut context, mut contract) = setup();
+        contract.set_withdrawal_threshold(None);
+        contract.set_withdrawal_delay(U64(0));
+        assert_eq!(contract.get_withdrawal_threshold(), Some(U128(1_000)));
+        assert_eq!(contract.get_withdrawal_delay().0, 100);
+        assert_eq!(contract.get_pending_withdrawal_threshold(), Some((None, U64(1_100))));
+
+        // Tightening applies right away.
+        contract.set_withdrawal_delay(U64(200));
+        assert_eq!(contract.get_withdrawal_delay().0, 200);
+        assert!(contract.get_pending_withdrawal_delay().is_none());
+
+        testing_env!(context.block_timestamp(1_100).build());
+        assert_eq!(contract.get_withdrawal_threshold(), None);
+        assert!(contract.get_pending_withdrawal_threshold().is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers above the withdrawal threshold must be queued")]
+    fn test_removed_threshold_still_applies() {
+        let (mut context, mut contract) = setup();
+        contract.set_withdrawal_threshold(None);
+        testing_env!(context.attached_deposit(1).block_timestamp(1_099).build());
+        contract.ft_transfer(accounts(1), U128(5_000), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Transfers above the withdrawal threshold must be queued")]
+    fn test_note_transfer_above_threshold() {
+        let (mut context, mut contract) = setup();
+        testing_env!(context.attached_deposit(1).build());
+        contract.ft_transfer_with_note(
+            accounts(1),
+            U128(5_000),
+            None,
+            "invoice".to_string(),
+            "42".to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo required")]
+    fn test_withdrawal_keeps_memo_rules() {
+        let (mut context, mut contract) = setup();
+        contract.set_require_memo(true);
+        let id = contract.queue_withdrawal(accounts(1), U128(5_000), None);
+
+        testing_env!(context
+            .storage_usage(env::storage_usage())
+            .attached_deposit(1)
+            .block_timestamp(1_100)
+            .build());
+        contract.execute_withdrawal(id);
+    }
+}